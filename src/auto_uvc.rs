@@ -4,16 +4,28 @@ use tokio::sync::mpsc;
 use crate::uvc;
 use crate::protos;
 use crate::presetdb;
+use crate::config;
 use crate::uvierror::UVIError;
 use tokio::time;
 use std::time::Duration;
 
+const MOVE_TIMEOUT: Duration = Duration::from_secs(10);
+const LED_ON: i64 = 1; // Led1Mode
+const RESET_STEP_TIMEOUT: Duration = Duration::from_secs(3);
+// the three steps of a reset, longer than a move
+const RESET_TIMEOUT: Duration = Duration::from_secs(RESET_STEP_TIMEOUT.as_secs()*3 + 5);
+// preset recalls without a speed: VISCA speeds 18 (pan) and 14 (tilt)
+const DEFAULT_PRESET_SPEED: protos::PanTilt = protos::PanTilt { pan: 0x18*3600*4, tilt: 0x14*3600 };
+
 #[derive(Default,Debug)]
 struct CamCtrl {
   minimum: i64,
   maximum: i64,
   value: i64,
-  _step: u64,
+  step: u64,
+  default: i64,
+  low: i64,  // limits set by the user, inside minimum and maximum
+  high: i64,
 }
 
 impl CamCtrl {
@@ -24,20 +36,50 @@ impl CamCtrl {
       minimum: ctrl.minimum,
       maximum: ctrl.maximum,
       value: value,
-      _step: ctrl.step
+      step: ctrl.step,
+      default: ctrl.default,
+      low: ctrl.minimum,
+      high: ctrl.maximum
     })
   }
-  fn set(&mut self, newval: i64) {
-    if newval > self.maximum {
-      self.value = self.maximum;
+  fn limit(&self, newval: i64) -> i64 {
+    if newval > self.high {
+      self.high
     } else {
-      if newval < self.minimum {
-        self.value = self.minimum;
+      if newval < self.low {
+        self.low
       } else {
-        self.value = newval;
+        newval
       }
     }
   }
+  fn set(&mut self, newval: i64) {
+    self.value = self.limit(newval);
+  }
+  fn at_limit(&self) -> bool {
+    self.value >= self.high || self.value <= self.low
+  }
+  // None: back to the range of the device
+  fn set_limits(&mut self, low: Option<i64>, high: Option<i64>) {
+    let low = std::cmp::max(low.unwrap_or(self.minimum), self.minimum);
+    let high = std::cmp::min(high.unwrap_or(self.maximum), self.maximum);
+    self.low = std::cmp::min(low, high);
+    self.high = std::cmp::max(low, high);
+  }
+  // value at a position in the range, 0 to 1.0
+  fn from_fraction(&self, f: f64) -> i64 {
+    self.minimum + (((self.maximum-self.minimum) as f64)*f).round() as i64
+  }
+  // position of a read back value in the range, 0 to 1.0
+  fn fraction(&self, readback: i64) -> f64 {
+    if self.maximum <= self.minimum { return 0.0; }
+    ((readback-self.minimum) as f64)/((self.maximum-self.minimum) as f64)
+  }
+  // read back value close enough to the commanded one
+  fn near(&self, readback: i64) -> bool {
+    let tolerance = std::cmp::max(self.step as i64, (self.maximum-self.minimum)/200);
+    (readback-self.value).abs() <= tolerance
+  }
 }
 
 #[derive(Debug)]
@@ -46,6 +88,18 @@ struct PanTilt {
   tilt: CamCtrl,
   panspeed: i64,
   tiltspeed: i64,
+  target: Option<protos::PanTilt>, // where the speeds stop
+  inverted: (bool, bool), // image flipped: pan, tilt follow the picture, not the motor
+  reset_ctrl: bool, // the device has pan/tilt reset controls
+  reset: Option<(ResetStep, time::Instant)>, // recalibration running, step and its deadline
+}
+
+// recalibration: both mechanical ends, then the centre
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ResetStep {
+  Minimum,
+  Maximum,
+  Centre,
 }
 
 impl PanTilt {
@@ -55,11 +109,18 @@ impl PanTilt {
       tilt: CamCtrl::init(&cam, uvc::CamControl::TiltAbsolute).await?,
       panspeed: 0,
       tiltspeed: 0,    
+      target: None,
+      inverted: (false, false),
+      reset_ctrl: cam.get_ctrl_descr(uvc::CamControl::PanReset).await.is_ok() &&
+        cam.get_ctrl_descr(uvc::CamControl::TiltReset).await.is_ok(),
+      reset: None,
     })
   }
   async fn absolute_move(&mut self, cam: &uvc::Camera, pan:i64, tilt:i64) -> Result<(), UVIError> {
     self.panspeed = 0;
     self.tiltspeed = 0;
+    self.target = None;
+    self.reset = None;
     self.pan.set(pan);
     cam.set_ctrl(uvc::CamControl::PanAbsolute, self.pan.value).await?;
     self.tilt.set(tilt);
@@ -68,20 +129,102 @@ impl PanTilt {
   }
   async fn relative_move(&mut self, cam: &uvc::Camera, pan_move:i64, tilt_move:i64) -> Result<(), UVIError> {
     self.pan.set(self.pan.value + pan_move);
-    if self.pan.value >= self.pan.maximum || self.pan.value <= self.pan.minimum {
+    if self.pan.at_limit() {
       self.panspeed = 0;
     }
     cam.set_ctrl(uvc::CamControl::PanAbsolute, self.pan.value).await?;
     self.tilt.set(self.tilt.value + tilt_move);
-    if self.tilt.value >= self.tilt.maximum || self.tilt.value <= self.tilt.minimum {
+    if self.tilt.at_limit() {
       self.tiltspeed = 0;
     }
     cam.set_ctrl(uvc::CamControl::TiltAbsolute, self.tilt.value).await?;
     Ok(())
   }
+  // travel to the target at the speeds, step by step in periodic_move
+  async fn move_to(&mut self, cam: &uvc::Camera, pan:i64, tilt:i64, panspeed:i64, tiltspeed:i64) -> Result<(), UVIError> {
+    self.reset = None;
+    let pan = self.pan.limit(pan);
+    let tilt = self.tilt.limit(tilt);
+    self.panspeed = panspeed.abs() * (pan-self.pan.value).signum();
+    self.tiltspeed = tiltspeed.abs() * (tilt-self.tilt.value).signum();
+    self.target = Some(protos::PanTilt { pan: pan, tilt: tilt });
+    self.periodic_move(cam).await
+  }
+  // directions of the joystick (continuous and relative moves)
+  fn directions(&self, pan: i64, tilt: i64) -> (i64, i64) {
+    (if self.inverted.0 {-pan} else {pan}, if self.inverted.1 {-tilt} else {tilt})
+  }
+  // corners DownLeft (low) and UpRight (high)
+  fn set_limits(&mut self, downleft: Option<(i64, i64)>, upright: Option<(i64, i64)>) {
+    self.pan.set_limits(downleft.map(|l| l.0), upright.map(|l| l.0));
+    self.tilt.set_limits(downleft.map(|l| l.1), upright.map(|l| l.1));
+  }
+  // the cached position is not trusted anymore: the device finds its origin again
+  async fn start_reset(&mut self, cam: &uvc::Camera) -> Result<(), UVIError> {
+    self.panspeed = 0;
+    self.tiltspeed = 0;
+    self.target = None;
+    if self.reset_ctrl {
+      cam.set_ctrl(uvc::CamControl::PanReset, 1).await?;
+      cam.set_ctrl(uvc::CamControl::TiltReset, 1).await?;
+      self.pan.value = 0;
+      self.tilt.value = 0;
+      self.reset = Some((ResetStep::Centre, time::Instant::now() + RESET_STEP_TIMEOUT));
+      Ok(())
+    } else {
+      self.reset_step(cam, ResetStep::Minimum).await
+    }
+  }
+  // outside the limits of the user, the whole range of the device
+  async fn reset_step(&mut self, cam: &uvc::Camera, step: ResetStep) -> Result<(), UVIError> {
+    let (pan, tilt) = match step {
+      ResetStep::Minimum => (self.pan.minimum, self.tilt.minimum),
+      ResetStep::Maximum => (self.pan.maximum, self.tilt.maximum),
+      ResetStep::Centre => (0i64.clamp(self.pan.minimum, self.pan.maximum), 0i64.clamp(self.tilt.minimum, self.tilt.maximum))
+    };
+    self.pan.value = pan;
+    cam.set_ctrl(uvc::CamControl::PanAbsolute, pan).await?;
+    self.tilt.value = tilt;
+    cam.set_ctrl(uvc::CamControl::TiltAbsolute, tilt).await?;
+    self.reset = Some((step, time::Instant::now() + RESET_STEP_TIMEOUT));
+    Ok(())
+  }
+  async fn periodic_reset(&mut self, cam: &uvc::Camera) -> Result<(), UVIError> {
+    let (step, deadline) = match self.reset {
+      Some(reset) => reset,
+      None => return Ok(())
+    };
+    let pan = cam.get_ctrl(uvc::CamControl::PanAbsolute).await?;
+    let tilt = cam.get_ctrl(uvc::CamControl::TiltAbsolute).await?;
+    if !(self.pan.near(pan) && self.tilt.near(tilt)) && time::Instant::now() < deadline {
+      return Ok(());
+    }
+    match step {
+      ResetStep::Minimum => self.reset_step(cam, ResetStep::Maximum).await,
+      ResetStep::Maximum => self.reset_step(cam, ResetStep::Centre).await,
+      ResetStep::Centre => { // where the motors really are
+        self.pan.value = pan;
+        self.tilt.value = tilt;
+        self.reset = None;
+        Ok(())
+      }
+    }
+  }
+  async fn reached(&self, cam: &uvc::Camera) -> Result<bool, UVIError> {
+    Ok(self.reset.is_none() && self.panspeed == 0 && self.tiltspeed == 0 &&
+      self.pan.near(cam.get_ctrl(uvc::CamControl::PanAbsolute).await?) &&
+      self.tilt.near(cam.get_ctrl(uvc::CamControl::TiltAbsolute).await?))
+  }
+  // freeze where the motors are now
+  async fn stop(&mut self, cam: &uvc::Camera) -> Result<(), UVIError> {
+    let pan = cam.get_ctrl(uvc::CamControl::PanAbsolute).await?;
+    let tilt = cam.get_ctrl(uvc::CamControl::TiltAbsolute).await?;
+    self.absolute_move(cam, pan, tilt).await
+  }
   async fn periodic_move(&mut self, cam: &uvc::Camera) -> Result<(), UVIError> {
     // seconds(degree/3600) per second -> /20 for each 50ms
     let mut pan_move = self.panspeed/20; let mut tilt_move = self.tiltspeed/20;
+    if self.panspeed == 0 && self.tiltspeed == 0 { self.target = None; } // already at the target
     if pan_move != 0 || tilt_move != 0 {
       if pan_move != 0 {
         let pan_absolute = cam.get_ctrl(uvc::CamControl::PanAbsolute).await?;
@@ -93,6 +236,18 @@ impl PanTilt {
         let tiltdelta = (tilt_absolute-self.tilt.value).abs();
         if tiltdelta > 2*60*60 { tilt_move = 0; }; // 2 degrees
       }
+      if let Some(target) = &self.target {
+        // the last step lands on the target
+        if self.panspeed != 0 && (target.pan-self.pan.value).abs() <= pan_move.abs() {
+          pan_move = target.pan-self.pan.value;
+          self.panspeed = 0;
+        }
+        if self.tiltspeed != 0 && (target.tilt-self.tilt.value).abs() <= tilt_move.abs() {
+          tilt_move = target.tilt-self.tilt.value;
+          self.tiltspeed = 0;
+        }
+        if self.panspeed == 0 && self.tiltspeed == 0 { self.target = None; }
+      }
       self.relative_move(&cam, pan_move, tilt_move).await?;
     }
     Ok(())
@@ -103,6 +258,8 @@ impl PanTilt {
 struct Zoom {
   zoom: CamCtrl,
   zoomspeed: i64,
+  target: Option<i64>, // where the speed stops
+  optical: Option<i64>, // ZoomAbsolute where the digital zoom starts
 }
 
 impl Zoom {
@@ -110,21 +267,70 @@ impl Zoom {
     Ok(Zoom {
       zoom: CamCtrl::init(&cam, uvc::CamControl::ZoomAbsolute).await?,
       zoomspeed: 0,
+      target: None,
+      optical: None,
     })
   }
+  // config.txt: optical_zoom_max = ZoomAbsolute at the tele end of the lens
+  fn configure(&mut self, ncam: u8) {
+    let cfg = config::settings();
+    self.optical = match cfg.get_u32(&cfg.cam_key("optical_zoom_max", ncam)).map(|o| o as i64) {
+      Some(o) if o <= self.zoom.minimum || o >= self.zoom.maximum => {
+        eprintln!("config.txt: optical_zoom_max out of the zoom range {}-{}", self.zoom.minimum, self.zoom.maximum);
+        None
+      },
+      o => o
+    };
+  }
+  // digital zoom off: limited to the optical range
+  async fn digital(&mut self, cam: &uvc::Camera, on: bool) -> Result<(), UVIError> {
+    let optical = self.optical.ok_or(UVIError::CamControlNotFound)?;
+    self.zoom.set_limits(None, if on { None } else { Some(optical) });
+    if self.zoom.value > self.zoom.high {
+      self.absolute(cam, self.zoom.value).await?;
+    }
+    Ok(())
+  }
+  fn digital_on(&self) -> Option<bool> {
+    self.optical.map(|_o| self.zoom.high == self.zoom.maximum)
+  }
   async fn absolute(&mut self, cam: &uvc::Camera, zoom:i64) -> Result<(), UVIError> {
     self.zoomspeed = 0;
+    self.target = None;
     self.zoom.set(zoom);
     cam.set_ctrl(uvc::CamControl::ZoomAbsolute, self.zoom.value).await?;
     Ok(())
   }
+  async fn reached(&self, cam: &uvc::Camera) -> Result<bool, UVIError> {
+    Ok(self.zoomspeed == 0 && self.zoom.near(cam.get_ctrl(uvc::CamControl::ZoomAbsolute).await?))
+  }
+  async fn stop(&mut self, cam: &uvc::Camera) -> Result<(), UVIError> {
+    let zoom = cam.get_ctrl(uvc::CamControl::ZoomAbsolute).await?;
+    self.absolute(cam, zoom).await
+  }
+  // travel to the target in steps of zoomspeed, in periodic_move
+  async fn move_to(&mut self, cam: &uvc::Camera, zoom:i64, zoomspeed:i64) -> Result<(), UVIError> {
+    let zoom = self.zoom.limit(zoom);
+    self.zoomspeed = std::cmp::max(zoomspeed.abs(), 1) * (zoom-self.zoom.value).signum();
+    self.target = if self.zoomspeed != 0 { Some(zoom) } else { None };
+    self.periodic_move(cam).await
+  }
   async fn periodic_move(&mut self, cam: &uvc::Camera) -> Result<(), UVIError> {
     if self.zoomspeed != 0 {
       let zoom_absolute = cam.get_ctrl(uvc::CamControl::ZoomAbsolute).await?;
       let zoomdelta = (zoom_absolute-self.zoom.value).abs();
       if zoomdelta < (self.zoom.maximum-self.zoom.minimum)/10 {
-        self.zoom.set(self.zoom.value + self.zoomspeed);
-        if self.zoom.value >= self.zoom.maximum || self.zoom.value <= self.zoom.minimum {
+        let mut zoom_move = self.zoomspeed;
+        if let Some(target) = self.target {
+          // the last step lands on the target
+          if (target-self.zoom.value).abs() <= zoom_move.abs() {
+            zoom_move = target-self.zoom.value;
+            self.zoomspeed = 0;
+            self.target = None;
+          }
+        }
+        self.zoom.set(self.zoom.value + zoom_move);
+        if self.zoom.at_limit() {
           self.zoomspeed = 0;
         }
         cam.set_ctrl(uvc::CamControl::ZoomAbsolute, self.zoom.value).await?;
@@ -134,11 +340,63 @@ impl Zoom {
   }
 }
 
+// one push: auto until the read back settles, then locked
+#[derive(Debug)]
+struct OnePush {
+  done: protos::Completion,
+  started: time::Instant,
+  stable: u32, // ticks with the same read back
+}
+
+const ONE_PUSH_MIN: Duration = Duration::from_secs(1);
+const ONE_PUSH_MAX: Duration = Duration::from_secs(5);
+const ONE_PUSH_STABLE_TICKS: u32 = 10;
+
+impl OnePush {
+  fn new(done: protos::Completion) -> OnePush {
+    OnePush {
+      done: done,
+      started: time::Instant::now(),
+      stable: 0
+    }
+  }
+  // true when it is time to lock
+  fn settled(&mut self, same: bool) -> bool {
+    self.stable = if same { self.stable + 1 } else { 0 };
+    let elapsed = self.started.elapsed();
+    (elapsed >= ONE_PUSH_MIN && self.stable >= ONE_PUSH_STABLE_TICKS) || elapsed >= ONE_PUSH_MAX
+  }
+}
+
+// VISCA AF modes
+const AF_NORMAL: u8 = 0;
+const AF_INTERVAL: u8 = 1;
+const AF_ZOOM_TRIGGER: u8 = 2;
+const AF_ZOOM_TRIGGER_RUN: Duration = Duration::from_secs(3);
+// low sensitivity: locked once converged, for a while
+const AF_LOW_HOLD: Duration = Duration::from_secs(3);
+const AF_LOW_MAX_RUN: Duration = Duration::from_secs(10);
+const AF_LOW_STABLE_TICKS: u32 = 10;
+
+// autofocus of the camera switched on and off by the AF modes, the focus mode stays auto
+#[derive(Debug, Clone, Copy)]
+enum AfPhase {
+  Running { until: time::Instant, stable: u32 },
+  Locked { until: Option<time::Instant> }, // None: until the next zoom move
+}
+
 #[derive(Debug)]
 struct Focus {
   auto: CamCtrl,
   focus: CamCtrl,
   focusspeed: i64,
+  onepush: Option<OnePush>,
+  af_mode: u8,
+  af_low: bool, // sensitivity
+  af_interval: (u8, u8), // seconds: AF active, then staying
+  af_phase: Option<AfPhase>,
+  zoom_seen: Option<i64>, // zoom on the last tick, to find the end of the zoom moves
+  zooming: bool,
 }
 
 impl Focus {
@@ -146,11 +404,20 @@ impl Focus {
     Ok(Focus {
       auto: CamCtrl::init(&cam, uvc::CamControl::FocusAuto).await?,
       focus: CamCtrl::init(&cam, uvc::CamControl::FocusAbsolute).await?,
-      focusspeed: 0
+      focusspeed: 0,
+      onepush: None,
+      af_mode: AF_NORMAL,
+      af_low: false,
+      af_interval: (2, 5),
+      af_phase: None,
+      zoom_seen: None,
+      zooming: false,
     })
   }
   async fn absolute(&mut self, cam: &uvc::Camera, auto: bool, focus:i64) -> Result<(), UVIError> {
+    self.end_one_push();
     self.focusspeed = 0;
+    self.af_phase = None;
     self.auto.set(if auto {1} else {0});
     self.focus.set(focus);
     cam.set_ctrl(uvc::CamControl::FocusAuto, self.auto.value).await?;
@@ -159,6 +426,17 @@ impl Focus {
     }
     Ok(())
   }
+  async fn reached(&self, cam: &uvc::Camera) -> Result<bool, UVIError> {
+    if self.auto.value > 0 { return Ok(true); } // autofocus: nothing to wait for
+    Ok(self.focusspeed == 0 && self.focus.near(cam.get_ctrl(uvc::CamControl::FocusAbsolute).await?))
+  }
+  async fn stop(&mut self, cam: &uvc::Camera) -> Result<(), UVIError> {
+    self.focusspeed = 0;
+    if self.auto.value > 0 { return Ok(()); } // the camera drives the focus
+    self.focus.set(cam.get_ctrl(uvc::CamControl::FocusAbsolute).await?);
+    cam.set_ctrl(uvc::CamControl::FocusAbsolute, self.focus.value).await?;
+    Ok(())
+  }
   async fn periodic_move(&mut self, cam: &uvc::Camera) -> Result<(), UVIError> {
     if self.focusspeed != 0 {
       let focus_absolute = cam.get_ctrl(uvc::CamControl::FocusAbsolute).await?;
@@ -173,19 +451,140 @@ impl Focus {
     }
     Ok(())
   }
+  fn end_one_push(&mut self) {
+    if let Some(onepush) = self.onepush.take() {
+      onepush.done.send(Err(UVIError::CommandCancelled));
+    }
+  }
+  // a new AF mode or sensitivity starts with the autofocus running
+  async fn set_af(&mut self, cam: &uvc::Camera, mode: u8, low: bool) -> Result<(), UVIError> {
+    if mode > AF_ZOOM_TRIGGER { return Err(UVIError::CommandNotSupported); }
+    self.af_mode = mode;
+    self.af_low = low;
+    self.af_phase = None;
+    if self.auto.value > 0 && self.onepush.is_none() {
+      cam.set_ctrl(uvc::CamControl::FocusAuto, 1).await?;
+    }
+    Ok(())
+  }
+  fn set_af_interval(&mut self, active: u8, staying: u8) {
+    self.af_interval = (std::cmp::max(active, 1), std::cmp::max(staying, 1));
+    self.af_phase = None;
+  }
+  fn af_run_time(&self) -> Duration {
+    match self.af_mode {
+      AF_INTERVAL => Duration::from_secs(self.af_interval.0 as u64),
+      AF_ZOOM_TRIGGER => AF_ZOOM_TRIGGER_RUN,
+      _ => AF_LOW_MAX_RUN
+    }
+  }
+  fn af_hold_time(&self) -> Option<Duration> {
+    match self.af_mode {
+      AF_INTERVAL => Some(Duration::from_secs(self.af_interval.1 as u64)),
+      AF_ZOOM_TRIGGER => None,
+      _ => Some(AF_LOW_HOLD)
+    }
+  }
+  // timed autofocus of the interval and zoom trigger modes and of the low sensitivity
+  async fn periodic_af(&mut self, cam: &uvc::Camera, zoom: Option<i64>) -> Result<(), UVIError> {
+    let moved = zoom != self.zoom_seen;
+    let zoom_stopped = self.zooming && !moved;
+    self.zoom_seen = zoom;
+    self.zooming = moved;
+    if self.auto.value == 0 || self.onepush.is_some() || (self.af_mode == AF_NORMAL && !self.af_low) {
+      return Ok(());
+    }
+    let now = time::Instant::now();
+    let phase = match self.af_phase {
+      Some(phase) => phase,
+      None => {
+        cam.set_ctrl(uvc::CamControl::FocusAuto, 1).await?;
+        AfPhase::Running { until: now + self.af_run_time(), stable: 0 }
+      }
+    };
+    self.af_phase = Some(match phase {
+      AfPhase::Running { until, stable } => {
+        let focus = cam.get_ctrl(uvc::CamControl::FocusAbsolute).await?;
+        let stable = if self.focus.near(focus) { stable + 1 } else { 0 };
+        self.focus.value = focus;
+        if now >= until || (self.af_low && stable >= AF_LOW_STABLE_TICKS) {
+          // locked where the autofocus left it
+          cam.set_ctrl(uvc::CamControl::FocusAuto, 0).await?;
+          cam.set_ctrl(uvc::CamControl::FocusAbsolute, focus).await?;
+          AfPhase::Locked { until: self.af_hold_time().map(|t| now + t) }
+        } else {
+          AfPhase::Running { until, stable }
+        }
+      },
+      AfPhase::Locked { until } => {
+        let wake = match until {
+          Some(until) => now >= until,
+          None => zoom_stopped
+        };
+        if wake {
+          cam.set_ctrl(uvc::CamControl::FocusAuto, 1).await?;
+          AfPhase::Running { until: now + self.af_run_time(), stable: 0 }
+        } else {
+          AfPhase::Locked { until }
+        }
+      }
+    });
+    Ok(())
+  }
+  async fn one_push(&mut self, cam: &uvc::Camera, done: protos::Completion) -> Result<(), UVIError> {
+    if let Err(e) = self.absolute(cam, true, self.focus.value).await {
+      return finish(done, Err(e));
+    }
+    self.onepush = Some(OnePush::new(done));
+    Ok(())
+  }
+  // the autofocus converged: back to manual on its position
+  async fn check_one_push(&mut self, cam: &uvc::Camera) {
+    let onepush = match self.onepush.as_mut() {
+      Some(onepush) => onepush,
+      None => return
+    };
+    let cancelled = onepush.done.cancelled();
+    let focus = match cam.get_ctrl(uvc::CamControl::FocusAbsolute).await {
+      Ok(focus) => focus,
+      Err(e) => {
+        if let Some(onepush) = self.onepush.take() { onepush.done.send(Err(e)); }
+        return;
+      }
+    };
+    let settled = onepush.settled(self.focus.near(focus));
+    self.focus.value = focus;
+    if settled || cancelled {
+      let onepush = self.onepush.take();
+      let r = self.absolute(cam, false, focus).await;
+      if let Some(onepush) = onepush {
+        onepush.done.send(if cancelled {Err(UVIError::CommandCancelled)} else {r});
+      }
+    }
+  }
 }
 
 #[derive(Debug)]
 struct WhiteBal {
   auto: CamCtrl,
-  temp: CamCtrl
+  temp: CamCtrl,
+  red: Option<CamCtrl>,
+  blue: Option<CamCtrl>,
+  mode: u8, // VISCA white balance mode
+  onepush: Option<OnePush>,
 }
 
 impl WhiteBal {
   async fn init(cam: &uvc::Camera) -> Result<WhiteBal, UVIError> {
+    let auto = CamCtrl::init(&cam, uvc::CamControl::WhiteBalanceTemperatureAuto).await?;
+    let mode = if auto.value > 0 {0} else {5};
     Ok(WhiteBal {
-      auto: CamCtrl::init(&cam, uvc::CamControl::WhiteBalanceTemperatureAuto).await?,
-      temp: CamCtrl::init(&cam, uvc::CamControl::WhiteBalanceTemperature).await?
+      auto: auto,
+      temp: CamCtrl::init(&cam, uvc::CamControl::WhiteBalanceTemperature).await?,
+      red: optional(CamCtrl::init(&cam, uvc::CamControl::RedBalance).await)?,
+      blue: optional(CamCtrl::init(&cam, uvc::CamControl::BlueBalance).await)?,
+      mode: mode,
+      onepush: None,
     })
   }
   async fn absolute(&mut self, cam: &uvc::Camera, auto: bool, temp:i64) -> Result<(), UVIError> {
@@ -197,6 +596,268 @@ impl WhiteBal {
     }
     Ok(())
   }
+  // keep the temperature the camera is using now
+  async fn lock(&mut self, cam: &uvc::Camera) -> Result<(), UVIError> {
+    let temp = cam.get_ctrl(uvc::CamControl::WhiteBalanceTemperature).await.unwrap_or(self.temp.value);
+    self.absolute(cam, false, temp).await?;
+    self.mode = 3;
+    Ok(())
+  }
+  fn end_one_push(&mut self) {
+    if let Some(onepush) = self.onepush.take() {
+      onepush.done.send(Err(UVIError::CommandCancelled));
+    }
+  }
+  async fn set_mode(&mut self, cam: &uvc::Camera, mode: u8) -> Result<(), UVIError> {
+    self.end_one_push();
+    match mode {
+      0 | 4 => self.absolute(cam, true, self.temp.value).await?, // Auto, Auto Tracing
+      1 => self.absolute(cam, false, 3200).await?, // Indoor
+      2 => self.absolute(cam, false, 5800).await?, // Outdoor
+      3 => self.lock(cam).await?, // One Push, until the next trigger
+      5 => self.absolute(cam, false, self.temp.value).await?, // Manual
+      _ => return Err(UVIError::CommandNotSupported)
+    }
+    self.mode = mode;
+    Ok(())
+  }
+  async fn one_push(&mut self, cam: &uvc::Camera, done: protos::Completion) -> Result<(), UVIError> {
+    self.end_one_push();
+    if let Err(e) = self.absolute(cam, true, self.temp.value).await {
+      return finish(done, Err(e));
+    }
+    self.onepush = Some(OnePush::new(done));
+    Ok(())
+  }
+  async fn check_one_push(&mut self, cam: &uvc::Camera) {
+    let onepush = match self.onepush.as_mut() {
+      Some(onepush) => onepush,
+      None => return
+    };
+    if onepush.done.cancelled() {
+      self.lock(cam).await.ok();
+      self.end_one_push();
+      return;
+    }
+    let temp = match cam.get_ctrl(uvc::CamControl::WhiteBalanceTemperature).await {
+      Ok(temp) => temp,
+      Err(e) => {
+        if let Some(onepush) = self.onepush.take() { onepush.done.send(Err(e)); }
+        return;
+      }
+    };
+    let settled = onepush.settled(self.temp.near(temp));
+    self.temp.value = temp;
+    if settled {
+      let r = self.absolute(cam, false, temp).await;
+      self.mode = 3;
+      if let Some(onepush) = self.onepush.take() { onepush.done.send(r); }
+    }
+  }
+  // red, blue 0 to 1.0; without balance controls, from the temperature
+  fn gains(&self) -> (f64, f64) {
+    match (self.red.as_ref(), self.blue.as_ref()) {
+      (Some(red), Some(blue)) => (red.fraction(red.value), blue.fraction(blue.value)),
+      _ => {
+        let t = self.temp.fraction(self.temp.value);
+        (t, 1.0-t)
+      }
+    }
+  }
+  async fn set_gains(&mut self, cam: &uvc::Camera, red: Option<f64>, blue: Option<f64>) -> Result<(), UVIError> {
+    self.end_one_push();
+    let (r, b) = self.gains();
+    let (r, b) = (red.unwrap_or(r), blue.unwrap_or(b));
+    self.absolute(cam, false, self.temp.value).await?;
+    self.mode = 5;
+    match (self.red.as_mut(), self.blue.as_mut()) {
+      (Some(redctrl), Some(bluectrl)) => {
+        redctrl.set(redctrl.from_fraction(r));
+        cam.set_ctrl(uvc::CamControl::RedBalance, redctrl.value).await?;
+        bluectrl.set(bluectrl.from_fraction(b));
+        cam.set_ctrl(uvc::CamControl::BlueBalance, bluectrl.value).await?;
+      },
+      _ => { // one temperature for both: the gain given wins
+        let t = match (red, blue) {
+          (Some(r), None) => r,
+          (None, Some(b)) => 1.0-b,
+          _ => 0.5 + (r-b)/2.0
+        }.clamp(0.0, 1.0);
+        self.absolute(cam, false, self.temp.from_fraction(t)).await?;
+      }
+    }
+    Ok(())
+  }
+}
+
+#[derive(Debug)]
+struct Exposure {
+  auto: CamCtrl, // 1: manual, 3: aperture priority (automatic exposure time)
+  mode: u8, // VISCA AE mode
+  time: Option<CamCtrl>,
+  gain: Option<CamCtrl>,
+  brightness: Option<CamCtrl>,
+  backlight: Option<CamCtrl>,
+  exp_comp_on: bool,
+  exp_comp: f64, // -1 to 1 around the default brightness
+}
+
+impl Exposure {
+  async fn init(cam: &uvc::Camera) -> Result<Exposure, UVIError> {
+    let auto = CamCtrl::init(&cam, uvc::CamControl::ExposureAuto).await?;
+    Ok(Exposure {
+      mode: if auto.value == 1 {0x03} else {0x00},
+      auto: auto,
+      time: optional(CamCtrl::init(&cam, uvc::CamControl::ExposureAbsolute).await)?,
+      gain: optional(CamCtrl::init(&cam, uvc::CamControl::Gain).await)?,
+      brightness: optional(CamCtrl::init(&cam, uvc::CamControl::Brightness).await)?,
+      backlight: optional(CamCtrl::init(&cam, uvc::CamControl::BacklightCompensation).await)?,
+      exp_comp_on: false,
+      exp_comp: 0.0,
+    })
+  }
+  // the camera refuses manual values while automatic, they are kept until then
+  fn manual_time(&self) -> bool {
+    self.mode == 0x03 || self.mode == 0x0A // Manual, Shutter Priority
+  }
+  fn manual_gain(&self) -> bool {
+    self.mode == 0x03 // Manual
+  }
+  async fn set_mode(&mut self, cam: &uvc::Camera, mode: u8) -> Result<(), UVIError> {
+    let manual = match mode {
+      0x00 | 0x0B => false, // Full Auto, Iris Priority: UVC has no iris, same thing
+      0x03 | 0x0A => true,  // Manual, Shutter Priority
+      _ => return Err(UVIError::CommandNotSupported)
+    };
+    self.mode = mode;
+    self.auto.set(if manual {1} else {3});
+    cam.set_ctrl(uvc::CamControl::ExposureAuto, self.auto.value).await?;
+    if let (true, Some(time)) = (self.manual_time(), self.time.as_ref()) {
+      cam.set_ctrl(uvc::CamControl::ExposureAbsolute, time.value).await?;
+    }
+    if let (true, Some(gain)) = (self.manual_gain(), self.gain.as_ref()) {
+      cam.set_ctrl(uvc::CamControl::Gain, gain.value).await?;
+    }
+    Ok(())
+  }
+  async fn shutter(&mut self, cam: &uvc::Camera, secs: Option<f64>) -> Result<(), UVIError> {
+    let manual = self.manual_time();
+    let time = avail(&mut self.time)?;
+    time.set(secs.map_or(time.default, |s| (s*10000.0).round() as i64)); // 100 µs units
+    if manual {
+      cam.set_ctrl(uvc::CamControl::ExposureAbsolute, time.value).await?;
+    }
+    Ok(())
+  }
+  async fn gain(&mut self, cam: &uvc::Camera, f: Option<f64>) -> Result<(), UVIError> {
+    let manual = self.manual_gain();
+    let gain = avail(&mut self.gain)?;
+    gain.set(f.map_or(gain.default, |f| gain.from_fraction(f)));
+    if manual {
+      cam.set_ctrl(uvc::CamControl::Gain, gain.value).await?;
+    }
+    Ok(())
+  }
+  // the exposure compensation moves the brightness too, the last command wins
+  async fn brightness(&mut self, cam: &uvc::Camera, f: Option<f64>) -> Result<(), UVIError> {
+    let brightness = avail(&mut self.brightness)?;
+    brightness.set(f.map_or(brightness.default, |f| brightness.from_fraction(f)));
+    cam.set_ctrl(uvc::CamControl::Brightness, brightness.value).await?;
+    Ok(())
+  }
+  async fn exp_comp(&mut self, cam: &uvc::Camera, on: bool, comp: f64) -> Result<(), UVIError> {
+    let brightness = avail(&mut self.brightness)?;
+    self.exp_comp_on = on;
+    self.exp_comp = comp;
+    let shift = if on {(((brightness.maximum-brightness.minimum) as f64)*comp/2.0).round() as i64} else {0};
+    brightness.set(brightness.default + shift);
+    cam.set_ctrl(uvc::CamControl::Brightness, brightness.value).await?;
+    Ok(())
+  }
+  async fn backlight(&mut self, cam: &uvc::Camera, on: bool) -> Result<(), UVIError> {
+    let backlight = avail(&mut self.backlight)?;
+    backlight.set(if on {1} else {0});
+    cam.set_ctrl(uvc::CamControl::BacklightCompensation, backlight.value).await?;
+    Ok(())
+  }
+}
+
+// image quality, each control is optional
+#[derive(Debug)]
+struct Image {
+  sharpness: Option<CamCtrl>,
+  saturation: Option<CamCtrl>,
+  contrast: Option<CamCtrl>,
+  gamma: Option<CamCtrl>,
+}
+
+impl Image {
+  async fn init(cam: &uvc::Camera) -> Result<Image, UVIError> {
+    Ok(Image {
+      sharpness: optional(CamCtrl::init(&cam, uvc::CamControl::Sharpness).await)?,
+      saturation: optional(CamCtrl::init(&cam, uvc::CamControl::Saturation).await)?,
+      contrast: optional(CamCtrl::init(&cam, uvc::CamControl::Contrast).await)?,
+      gamma: optional(CamCtrl::init(&cam, uvc::CamControl::Gamma).await)?,
+    })
+  }
+  fn ctrl(&mut self, camctrl: uvc::CamControl) -> Option<&mut CamCtrl> {
+    match camctrl {
+      uvc::CamControl::Sharpness => self.sharpness.as_mut(),
+      uvc::CamControl::Saturation => self.saturation.as_mut(),
+      uvc::CamControl::Contrast => self.contrast.as_mut(),
+      uvc::CamControl::Gamma => self.gamma.as_mut(),
+      _ => None
+    }
+  }
+  async fn absolute(&mut self, cam: &uvc::Camera, camctrl: uvc::CamControl, value: i64) -> Result<(), UVIError> {
+    let ctrl = self.ctrl(camctrl).ok_or(UVIError::CamControlNotFound)?;
+    ctrl.set(value);
+    cam.set_ctrl(camctrl, ctrl.value).await
+  }
+  // 0 to 1.0 of the range, None: default
+  async fn direct(&mut self, cam: &uvc::Camera, camctrl: uvc::CamControl, f: Option<f64>) -> Result<(), UVIError> {
+    let ctrl = self.ctrl(camctrl).ok_or(UVIError::CamControlNotFound)?;
+    let value = f.map_or(ctrl.default, |f| ctrl.from_fraction(f));
+    self.absolute(cam, camctrl, value).await
+  }
+  fn fraction(ctrl: &Option<CamCtrl>) -> Option<f64> {
+    ctrl.as_ref().map(|c| c.fraction(c.value))
+  }
+}
+
+// command waiting for the end of the motion of some axes
+#[derive(Debug)]
+struct PendingMove {
+  pantilt: bool,
+  zoom: bool,
+  focus: bool,
+  done: protos::Completion,
+  deadline: time::Instant,
+}
+
+// optional component of the camera
+fn avail<T>(o: &mut Option<T>) -> Result<&mut T, UVIError> {
+  o.as_mut().ok_or(UVIError::CamControlNotFound)
+}
+
+// a missing control only disables that component of the camera
+fn optional<T>(r: Result<T, UVIError>) -> Result<Option<T>, UVIError> {
+  match r {
+    Ok(x) => Ok(Some(x)),
+    Err(UVIError::CamControlNotFound) => Ok(None),
+    Err(e) => Err(e)
+  }
+}
+
+// report the result of a command, failures of the device itself end the camera task
+fn finish(done: protos::Completion, r: Result<(), UVIError>) -> Result<(), UVIError> {
+  match r {
+    Err(e) if e.is_fatal() => Err(e),
+    r => {
+      done.send(r);
+      Ok(())
+    }
+  }
 }
 
 #[derive(Debug)]
@@ -204,15 +865,25 @@ pub struct AutoCamera {
   cam: uvc::Camera,
   presetdb: Option<presetdb::PresetDB>,
   pantilt: PanTilt,
-  zoom: Zoom,
-  focus: Focus,
-  whitebal: WhiteBal,
+  zoom: Option<Zoom>,
+  focus: Option<Focus>,
+  whitebal: Option<WhiteBal>,
+  exposure: Option<Exposure>,
+  hflip: Option<CamCtrl>,
+  vflip: Option<CamCtrl>,
+  image: Image,
+  led: Option<CamCtrl>, // mode at the start, back to it when out of air
+  tally: bool,
+  preset_speed: Option<protos::PanTilt>, // set by PresetSpeed, saved for the camera
+  last_preset: Option<u8>, // recalled or recorded, until the camera moves away
+  pending: Vec<PendingMove>,
 }
 
 impl fmt::Display for AutoCamera {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
       write!(f, "-Cam {} Pos:{},{},{},{}-", self.cam, self.pantilt.pan.value, 
-        self.pantilt.tilt.value, self.zoom.zoom.value, self.focus.focus.value)
+        self.pantilt.tilt.value, self.zoom.as_ref().map_or(0, |z| z.zoom.value),
+        self.focus.as_ref().map_or(0, |f| f.focus.value))
   }
 }
 
@@ -220,16 +891,29 @@ impl fmt::Display for AutoCamera {
 pub struct Preset {
   pub pan: i64, pub tilt: i64, pub zoom: i64,
   pub focusauto: bool, pub focus: i64, 
-  pub whitebalauto: bool, pub temperature: i64
+  pub whitebalauto: bool, pub temperature: i64,
+  pub sharpness: Option<i64>, pub saturation: Option<i64>,
+  pub contrast: Option<i64>, pub gamma: Option<i64>,
+  pub panspeed: Option<i64>, pub tiltspeed: Option<i64>
 }
 
 impl AutoCamera {
   pub async fn find_camera(ndev: u8) -> Result<(mpsc::UnboundedSender<protos::CamCmd>,String), UVIError> {
-    let cam = uvc::find_camera(ndev).await?;
-    let pantilt = PanTilt::init(&cam).await?;
-    let zoom = Zoom::init(&cam).await?;
-    let focus = Focus::init(&cam).await?;
-    let whitebal = WhiteBal::init(&cam).await?;
+    AutoCamera::start(uvc::find_camera(ndev).await?).await
+  }
+  // the task driving a device already found
+  pub async fn start(cam: uvc::Camera) -> Result<(mpsc::UnboundedSender<protos::CamCmd>,String), UVIError> {
+    let mut pantilt = PanTilt::init(&cam).await?;
+    let zoom = optional(Zoom::init(&cam).await)?;
+    let focus = optional(Focus::init(&cam).await)?;
+    let whitebal = optional(WhiteBal::init(&cam).await)?;
+    let exposure = optional(Exposure::init(&cam).await)?;
+    let hflip = optional(CamCtrl::init(&cam, uvc::CamControl::HorizontalFlip).await)?;
+    let vflip = optional(CamCtrl::init(&cam, uvc::CamControl::VerticalFlip).await)?;
+    let image = Image::init(&cam).await?;
+    let led = optional(CamCtrl::init(&cam, uvc::CamControl::Led1Mode).await)?;
+    pantilt.inverted = (hflip.as_ref().map_or(false, |f| f.value > 0),
+      vflip.as_ref().map_or(false, |f| f.value > 0));
     let (cam_chan, recv_cam_chan) = mpsc::unbounded_channel();
     let bus = cam.bus.to_string();
     let acam = AutoCamera {
@@ -239,6 +923,15 @@ impl AutoCamera {
       zoom: zoom,
       focus: focus,
       whitebal: whitebal,
+      exposure: exposure,
+      hflip: hflip,
+      vflip: vflip,
+      image: image,
+      led: led,
+      tally: false,
+      preset_speed: None,
+      last_preset: None,
+      pending: Vec::new(),
     };
     task::spawn(acam.run(recv_cam_chan));
     Ok((cam_chan,bus))
@@ -249,16 +942,30 @@ impl AutoCamera {
       tokio::select! {
         _ = tmr50ms.tick() => { // ignore errors here... too noisy
           self.pantilt.periodic_move(&self.cam).await.ok();
-          self.zoom.periodic_move(&self.cam).await.ok();
-          self.focus.periodic_move(&self.cam).await.ok();
+          self.pantilt.periodic_reset(&self.cam).await.ok();
+          if let Some(zoom) = self.zoom.as_mut() {
+            zoom.periodic_move(&self.cam).await.ok();
+          }
+          if let Some(focus) = self.focus.as_mut() {
+            focus.periodic_move(&self.cam).await.ok();
+            focus.check_one_push(&self.cam).await;
+            focus.periodic_af(&self.cam, self.zoom.as_ref().map(|z| z.zoom.value)).await.ok();
+          }
+          if let Some(whitebal) = self.whitebal.as_mut() {
+            whitebal.check_one_push(&self.cam).await;
+          }
+          self.check_pending().await;
         },
         Some(ev) = recv_cam_chan.recv() => {
           //println!("Ev: {:?}", ev);
           match self.run_ev(ev).await {
-            Err(e) => {
+            Err(e) if e.is_fatal() => {
               eprintln!("auto_uvc run err: {:?}", e);
               break;
             },
+            Err(e) => {
+              eprintln!("auto_uvc command err: {:?}", e);
+            },
             Ok(run) if run==false => break,
             _ => ()
           }              
@@ -267,117 +974,538 @@ impl AutoCamera {
       }
     }
   }
+  // a new motion on an axis ends the waiting of the previous command on it
+  fn wait_move(&mut self, pantilt: bool, zoom: bool, focus: bool, done: Option<protos::Completion>) {
+    let mut i = 0;
+    while i < self.pending.len() {
+      let p = &self.pending[i];
+      if (pantilt && p.pantilt) || (zoom && p.zoom) || (focus && p.focus) {
+        self.pending.remove(i).done.send(Ok(()));
+      } else {
+        i += 1;
+      }
+    }
+    if let Some(done) = done {
+      let timeout = if pantilt && self.pantilt.reset.is_some() {RESET_TIMEOUT} else {MOVE_TIMEOUT};
+      self.pending.push(PendingMove {
+        pantilt, zoom, focus, done,
+        deadline: time::Instant::now() + timeout
+      });
+    }
+  }
+  // like finish, but the completion waits for the motion of the axes
+  fn finish_move(&mut self, done: protos::Completion, r: Result<(), UVIError>,
+      pantilt: bool, zoom: bool, focus: bool) -> Result<(), UVIError> {
+    match r {
+      Ok(()) => {
+        self.wait_move(pantilt, zoom, focus, Some(done));
+        Ok(())
+      },
+      r => finish(done, r)
+    }
+  }
+  async fn check_pending(&mut self) {
+    let now = time::Instant::now();
+    let mut i = 0;
+    while i < self.pending.len() {
+      if self.pending[i].done.cancelled() {
+        let p = self.pending.remove(i);
+        // stop errors: the motion cannot be frozen anyway, still cancelled
+        if p.pantilt { self.pantilt.stop(&self.cam).await.ok(); }
+        if let (true, Some(zoom)) = (p.zoom, self.zoom.as_mut()) {
+          zoom.stop(&self.cam).await.ok();
+        }
+        if let (true, Some(focus)) = (p.focus, self.focus.as_mut()) {
+          focus.stop(&self.cam).await.ok();
+        }
+        p.done.send(Err(UVIError::CommandCancelled));
+        continue;
+      }
+      let p = &self.pending[i];
+      // read back errors: no way to know where the motor is, so done
+      let mut reached = !p.pantilt || self.pantilt.reached(&self.cam).await.unwrap_or(true);
+      if let (true, Some(zoom)) = (p.zoom, self.zoom.as_ref()) {
+        reached = reached && zoom.reached(&self.cam).await.unwrap_or(true);
+      }
+      if let (true, Some(focus)) = (p.focus, self.focus.as_ref()) {
+        reached = reached && focus.reached(&self.cam).await.unwrap_or(true);
+      }
+      if reached || now >= p.deadline {
+        self.pending.remove(i).done.send(Ok(()));
+      } else {
+        i += 1;
+      }
+    }
+  }
+
+  fn load_limits(&mut self) -> Result<(), UVIError> {
+    let presetdb = self.presetdb.as_ref().ok_or(UVIError::CameraNotFound)?;
+    let (downleft, upright) = (presetdb.limit(0)?, presetdb.limit(1)?);
+    self.pantilt.set_limits(downleft, upright);
+    Ok(())
+  }
+  fn set_limit(&mut self, corner: u8, pan: i64, tilt: i64) -> Result<(), UVIError> {
+    self.presetdb.as_ref().ok_or(UVIError::CameraNotFound)?.set_limit(corner, pan, tilt)?;
+    self.load_limits()
+  }
+  fn clear_limit(&mut self, corner: u8) -> Result<(), UVIError> {
+    self.presetdb.as_ref().ok_or(UVIError::CameraNotFound)?.clear_limit(corner)?;
+    self.load_limits()
+  }
+  fn load_preset_speed(&mut self) -> Result<(), UVIError> {
+    self.preset_speed = self.presetdb.as_ref().ok_or(UVIError::CameraNotFound)?.preset_speed()?;
+    Ok(())
+  }
+  fn set_preset_speed(&mut self, speed: protos::PanTilt) -> Result<(), UVIError> {
+    if let Some(presetdb) = self.presetdb.as_ref() { presetdb.set_preset_speed(&speed)?; }
+    self.preset_speed = Some(speed);
+    Ok(())
+  }
+  fn reset_preset(&mut self, npreset: u8) -> Result<(), UVIError> {
+    if self.last_preset == Some(npreset) { self.last_preset = None; }
+    self.presetdb.as_ref().ok_or(UVIError::CameraNotFound)?.clear(npreset)
+  }
+  fn record_preset(&mut self, npreset: u8, speed: Option<protos::PanTilt>) -> Result<(), UVIError> {
+    let preset = Preset {
+      pan: self.pantilt.pan.value,
+      tilt: self.pantilt.tilt.value,
+      zoom: self.zoom.as_ref().map_or(0, |z| z.zoom.value),
+      focusauto: self.focus.as_ref().map_or(true, |f| f.auto.value > 0),
+      focus: self.focus.as_ref().map_or(0, |f| f.focus.value),
+      whitebalauto: self.whitebal.as_ref().map_or(true, |w| w.auto.value > 0),
+      temperature: self.whitebal.as_ref().map_or(0, |w| w.temp.value),
+      sharpness: self.image.sharpness.as_ref().map(|c| c.value),
+      saturation: self.image.saturation.as_ref().map(|c| c.value),
+      contrast: self.image.contrast.as_ref().map(|c| c.value),
+      gamma: self.image.gamma.as_ref().map(|c| c.value),
+      panspeed: speed.as_ref().map(|s| s.pan),
+      tiltspeed: speed.as_ref().map(|s| s.tilt)
+    };
+    self.presetdb.as_ref().ok_or(UVIError::CameraNotFound)?.record(npreset, preset)?;
+    self.last_preset = Some(npreset);
+    Ok(())
+  }
+  async fn recover_preset(&mut self, npreset: u8) -> Result<(), UVIError> {
+    let opreset = self.presetdb.as_ref().ok_or(UVIError::CameraNotFound)?.recover(npreset)?;
+    match opreset {
+      Some(preset) => {
+        // speed of the preset, else of the camera
+        self.last_preset = Some(npreset);
+        let speed = match (preset.panspeed, preset.tiltspeed, self.preset_speed.as_ref()) {
+          (Some(pan), Some(tilt), _) => protos::PanTilt { pan: pan, tilt: tilt },
+          (_, _, Some(speed)) => protos::PanTilt { pan: speed.pan, tilt: speed.tilt },
+          _ => DEFAULT_PRESET_SPEED
+        };
+        let (pan, tilt) = (self.pantilt.pan.limit(preset.pan), self.pantilt.tilt.limit(preset.tilt));
+        // ticks of 50ms of the pan/tilt travel, the zoom arrives together
+        let ticks = std::cmp::max(
+          (pan-self.pantilt.pan.value).abs()*20/std::cmp::max(speed.pan, 1),
+          (tilt-self.pantilt.tilt.value).abs()*20/std::cmp::max(speed.tilt, 1));
+        self.pantilt.move_to(&self.cam, pan, tilt, speed.pan, speed.tilt).await?;
+        if let Some(zoom) = self.zoom.as_mut() {
+          let zoomspeed = (preset.zoom-zoom.zoom.value).abs()/std::cmp::max(ticks, 1);
+          zoom.move_to(&self.cam, preset.zoom, zoomspeed).await?;
+        }
+        if let Some(focus) = self.focus.as_mut() {
+          focus.absolute(&self.cam, preset.focusauto, preset.focus).await?;
+        }
+        if let Some(whitebal) = self.whitebal.as_mut() {
+          whitebal.end_one_push();
+          whitebal.absolute(&self.cam, preset.whitebalauto, preset.temperature).await?;
+          whitebal.mode = if preset.whitebalauto {0} else {5};
+        }
+        // presets older than the image settings have them NULL
+        for (camctrl, value) in [(uvc::CamControl::Sharpness, preset.sharpness),
+            (uvc::CamControl::Saturation, preset.saturation),
+            (uvc::CamControl::Contrast, preset.contrast), (uvc::CamControl::Gamma, preset.gamma)] {
+          if let (Some(value), Some(_)) = (value, self.image.ctrl(camctrl)) {
+            self.image.absolute(&self.cam, camctrl, value).await?;
+          }
+        }
+      },
+      _ => ()
+    }
+    Ok(())
+  }
+  async fn home(&mut self) -> Result<(), UVIError> {
+    self.pantilt.absolute_move(&self.cam, 0, 0).await?;
+    if let Some(zoom) = self.zoom.as_mut() {
+      zoom.absolute(&self.cam, zoom.zoom.minimum).await?;
+    }
+    if let Some(focus) = self.focus.as_mut() {
+      focus.absolute(&self.cam, true, focus.focus.value).await?;
+    }
+    Ok(())
+  }
+  async fn zoom_continuous(&mut self, zoom_f64: f64) -> Result<(), UVIError> { // -1 to 1
+    let zoom = avail(&mut self.zoom)?;
+    zoom.target = None;
+    zoom.zoomspeed = (((zoom.zoom.maximum-zoom.zoom.minimum) as f64)*
+      zoom_f64/20.0) as i64; // ops per 50ms
+    zoom.periodic_move(&self.cam).await
+  }
+  async fn zoom_direct(&mut self, zoom_f64: f64) -> Result<(), UVIError> { // 0 to 1.0
+    let zoom = avail(&mut self.zoom)?;
+    zoom.absolute(&self.cam, zoom.zoom.minimum+
+      (((zoom.zoom.maximum-zoom.zoom.minimum) as f64)*zoom_f64) as i64).await
+  }
+  // both moving together, nothing changed if one of them is missing
+  async fn zoom_focus_direct(&mut self, zoom_f64: f64, focus_f64: f64) -> Result<(), UVIError> {
+    if self.zoom.is_none() || self.focus.is_none() { return Err(UVIError::CamControlNotFound); }
+    self.zoom_direct(zoom_f64).await?;
+    self.focus_direct(focus_f64).await
+  }
+  async fn digital_zoom(&mut self, on: bool) -> Result<(), UVIError> {
+    avail(&mut self.zoom)?.digital(&self.cam, on).await
+  }
+  async fn auto_focus(&mut self, active: bool) -> Result<(), UVIError> {
+    let focus = avail(&mut self.focus)?;
+    focus.absolute(&self.cam, active, focus.focus.value).await
+  }
+  async fn auto_focus_toggle(&mut self) -> Result<(), UVIError> {
+    let focus = avail(&mut self.focus)?;
+    let active = if focus.auto.value == 0 {true} else {false};
+    focus.absolute(&self.cam, active, focus.focus.value).await
+  }
+  async fn focus_continuous(&mut self, focus_f64: f64) -> Result<(), UVIError> { // -1 to 1
+    let focus = avail(&mut self.focus)?;
+    focus.absolute(&self.cam, false, focus.focus.value).await?;
+    focus.focusspeed = (((focus.focus.maximum-focus.focus.minimum) as f64)*
+      focus_f64/20.0) as i64; // ops per 50ms
+    focus.periodic_move(&self.cam).await
+  }
+  async fn focus_direct(&mut self, focus_f64: f64) -> Result<(), UVIError> { // 1.0 (Near) - 0.0 (Far)
+    let focus = avail(&mut self.focus)?;
+    focus.absolute(&self.cam, true, focus.focus.minimum+
+      (((focus.focus.maximum-focus.focus.minimum) as f64)*focus_f64) as i64).await
+  }
+  async fn focus_one_push(&mut self, done: protos::Completion) -> Result<(), UVIError> {
+    match self.focus.as_mut() {
+      Some(focus) => focus.one_push(&self.cam, done).await,
+      None => finish(done, Err(UVIError::CamControlNotFound))
+    }
+  }
+  async fn af_mode(&mut self, mode: u8) -> Result<(), UVIError> {
+    let focus = avail(&mut self.focus)?;
+    focus.set_af(&self.cam, mode, focus.af_low).await
+  }
+  async fn af_sensitivity(&mut self, low: bool) -> Result<(), UVIError> {
+    let focus = avail(&mut self.focus)?;
+    focus.set_af(&self.cam, focus.af_mode, low).await
+  }
+  async fn zoom_position(&mut self) -> Result<f64, UVIError> { // 0 to 1.0
+    let zoom = avail(&mut self.zoom)?;
+    Ok(zoom.zoom.fraction(self.cam.get_ctrl(uvc::CamControl::ZoomAbsolute).await?))
+  }
+  async fn focus_position(&mut self) -> Result<f64, UVIError> { // 1.0 (Near) - 0.0 (Far)
+    let focus = avail(&mut self.focus)?;
+    // read back, the autofocus moves it by itself
+    Ok(focus.focus.fraction(self.cam.get_ctrl(uvc::CamControl::FocusAbsolute).await?))
+  }
+  async fn auto_exposure(&mut self, mode: u8) -> Result<(), UVIError> {
+    avail(&mut self.exposure)?.set_mode(&self.cam, mode).await
+  }
+  async fn shutter(&mut self, secs: Option<f64>) -> Result<(), UVIError> {
+    avail(&mut self.exposure)?.shutter(&self.cam, secs).await
+  }
+  async fn gain(&mut self, gain_f64: Option<f64>) -> Result<(), UVIError> {
+    avail(&mut self.exposure)?.gain(&self.cam, gain_f64).await
+  }
+  async fn brightness(&mut self, bright_f64: Option<f64>) -> Result<(), UVIError> {
+    avail(&mut self.exposure)?.brightness(&self.cam, bright_f64).await
+  }
+  // None keeps the current state or position
+  async fn exposure_compensation(&mut self, on: Option<bool>, comp: Option<f64>) -> Result<(), UVIError> {
+    let exposure = avail(&mut self.exposure)?;
+    let (on, comp) = (on.unwrap_or(exposure.exp_comp_on), comp.unwrap_or(exposure.exp_comp));
+    exposure.exp_comp(&self.cam, on, comp).await
+  }
+  async fn backlight(&mut self, on: bool) -> Result<(), UVIError> {
+    avail(&mut self.exposure)?.backlight(&self.cam, on).await
+  }
+  async fn flip(&mut self, h: Option<bool>, v: Option<bool>) -> Result<(), UVIError> {
+    if (h.is_some() && self.hflip.is_none()) || (v.is_some() && self.vflip.is_none()) {
+      return Err(UVIError::CamControlNotFound);
+    }
+    if let (Some(h), Some(hflip)) = (h, self.hflip.as_mut()) {
+      hflip.set(if h {1} else {0});
+      self.cam.set_ctrl(uvc::CamControl::HorizontalFlip, hflip.value).await?;
+      self.pantilt.inverted.0 = h;
+    }
+    if let (Some(v), Some(vflip)) = (v, self.vflip.as_mut()) {
+      vflip.set(if v {1} else {0});
+      self.cam.set_ctrl(uvc::CamControl::VerticalFlip, vflip.value).await?;
+      self.pantilt.inverted.1 = v;
+    }
+    Ok(())
+  }
+  fn focus_mode(&mut self) -> Result<bool, UVIError> { // true: auto
+    avail(&mut self.focus).map(|focus| if focus.auto.value>0 {true} else {false})
+  }
+  fn white_balance_state(&mut self) -> Result<u8, UVIError> {
+    avail(&mut self.whitebal).map(|whitebal| whitebal.mode)
+  }
+  async fn status(&mut self) -> Result<protos::CamStatus, UVIError> {
+    Ok(protos::CamStatus {
+      tally: self.tally,
+      zoom: optional(self.zoom_position().await)?,
+      zoom_optical: self.zoom.as_ref().and_then(|z| z.optical.map(|o| z.zoom.fraction(o))),
+      digital_zoom: self.zoom.as_ref().and_then(|z| z.digital_on()),
+      focus: optional(self.focus_position().await)?,
+      focus_auto: optional(self.focus_mode())?,
+      af_mode: self.focus.as_ref().map(|f| f.af_mode),
+      af_low: self.focus.as_ref().map(|f| f.af_low),
+      af_interval: self.focus.as_ref().map(|f| f.af_interval),
+      wb_mode: optional(self.white_balance_state())?,
+      red_gain: self.whitebal.as_ref().map(|w| w.gains().0),
+      blue_gain: self.whitebal.as_ref().map(|w| w.gains().1),
+      ae_mode: self.exposure.as_ref().map(|e| e.mode),
+      shutter: self.exposure.as_ref().and_then(|e| e.time.as_ref()).map(|t| (t.value as f64)/10000.0),
+      gain: self.exposure.as_ref().and_then(|e| e.gain.as_ref()).map(|g| g.fraction(g.value)),
+      bright: self.exposure.as_ref().and_then(|e| e.brightness.as_ref()).map(|b| b.fraction(b.value)),
+      exp_comp_on: self.exposure.as_ref().map(|e| e.exp_comp_on),
+      exp_comp: self.exposure.as_ref().map(|e| e.exp_comp),
+      backlight: self.exposure.as_ref().and_then(|e| e.backlight.as_ref()).map(|b| b.value > 0),
+      hflip: self.hflip.as_ref().map(|f| f.value > 0),
+      vflip: self.vflip.as_ref().map(|f| f.value > 0),
+      sharpness: Image::fraction(&self.image.sharpness),
+      saturation: Image::fraction(&self.image.saturation),
+      contrast: Image::fraction(&self.image.contrast),
+      gamma: Image::fraction(&self.image.gamma),
+      ..Default::default()
+    })
+  }
+  // on air: the LED of the camera on, if it has one
+  async fn tally(&mut self, on: bool) -> Result<(), UVIError> {
+    self.tally = on;
+    if let Some(led) = self.led.as_ref() {
+      self.cam.set_ctrl(uvc::CamControl::Led1Mode, if on { LED_ON } else { led.value }).await?;
+    }
+    Ok(())
+  }
+  async fn white_balance_mode(&mut self, wb: u8) -> Result<(), UVIError> {
+    avail(&mut self.whitebal)?.set_mode(&self.cam, wb).await
+  }
+  async fn white_balance_trigger(&mut self, done: protos::Completion) -> Result<(), UVIError> {
+    match self.whitebal.as_mut() {
+      Some(whitebal) => whitebal.one_push(&self.cam, done).await,
+      None => finish(done, Err(UVIError::CamControlNotFound))
+    }
+  }
+  async fn white_balance_gain(&mut self, red: Option<f64>, blue: Option<f64>) -> Result<(), UVIError> {
+    avail(&mut self.whitebal)?.set_gains(&self.cam, red, blue).await
+  }
+
   async fn run_ev(&mut self, ev: protos::CamCmd) -> Result<bool,UVIError> {
     match ev {
       protos::CamCmd::SetPresetNcam(ncam) => {
+        if let Some(zoom) = self.zoom.as_mut() { zoom.configure(ncam); }
         self.presetdb = Some(presetdb::connect_preset_db(ncam)?);
+        self.load_limits()?;
+        self.load_preset_speed()?;
       },
-      protos::CamCmd::ResetPreset(npreset) => {
-        self.presetdb.as_ref().ok_or(UVIError::CameraNotFound)?.clear(npreset)?;
+      protos::CamCmd::ResetPreset(npreset, done) => {
+        let r = self.reset_preset(npreset);
+        finish(done, r)?;
       },
-      protos::CamCmd::RecordPreset(npreset) => {
-        let preset = Preset {
-          pan: self.pantilt.pan.value,
-          tilt: self.pantilt.tilt.value,
-          zoom: self.zoom.zoom.value,
-          focusauto: if self.focus.auto.value > 0 {true} else {false},
-          focus: self.focus.focus.value,
-          whitebalauto: if self.whitebal.auto.value > 0 {true} else {false},
-          temperature: self.whitebal.temp.value
-        };
-        self.presetdb.as_ref().ok_or(UVIError::CameraNotFound)?.record(npreset, preset)?;
-      },
-      protos::CamCmd::RecoverPreset(npreset) => {
-        let opreset = self.presetdb.as_ref().ok_or(UVIError::CameraNotFound)?.recover(npreset)?;
-        match opreset {
-          Some(preset) => {
-            self.pantilt.absolute_move(&self.cam, preset.pan, preset.tilt).await?;
-            self.zoom.absolute(&self.cam, preset.zoom).await?;
-            self.focus.absolute(&self.cam, preset.focusauto, preset.focus).await?;
-            self.whitebal.absolute(&self.cam, preset.whitebalauto, preset.temperature).await?;
-          },
-          _ => ()
-        }
+      protos::CamCmd::RecordPreset(npreset, speed, done) => {
+        let r = self.record_preset(npreset, speed);
+        finish(done, r)?;
+      },
+      protos::CamCmd::RecoverPreset(npreset, done) => {
+        let r = self.recover_preset(npreset).await;
+        self.finish_move(done, r, true, true, true)?;
+      },
+      protos::CamCmd::Tally(on, done) => {
+        let r = self.tally(on).await;
+        finish(done, r)?;
+      },
+      protos::CamCmd::PresetSpeed(speed, done) => {
+        let r = self.set_preset_speed(speed);
+        finish(done, r)?;
+      },
+      protos::CamCmd::PanTiltLimitSet(corner, pantilt, done) => {
+        let r = self.set_limit(corner, pantilt.pan, pantilt.tilt);
+        finish(done, r)?;
       },
-      protos::CamCmd::Home() => {
-        self.pantilt.absolute_move(&self.cam, 0, 0).await?;
-        self.zoom.absolute(&self.cam, self.zoom.zoom.minimum).await?;
-        self.focus.absolute(&self.cam, true, self.focus.focus.value).await?;
+      protos::CamCmd::PanTiltLimitClear(corner, done) => {
+        let r = self.clear_limit(corner);
+        finish(done, r)?;
       },
-      protos::CamCmd::MoveContinuous(pantilt) => {
-        self.pantilt.panspeed = pantilt.pan; self.pantilt.tiltspeed = pantilt.tilt;
-        self.pantilt.periodic_move(&self.cam).await?;
+      protos::CamCmd::PanTiltReset(done) => {
+        self.last_preset = None;
+        let r = self.pantilt.start_reset(&self.cam).await;
+        self.finish_move(done, r, true, false, false)?;
       },
-      protos::CamCmd::MoveRelative(pantilt) => {
-        self.pantilt.relative_move(&self.cam, pantilt.pan, pantilt.tilt).await?;
+      protos::CamCmd::Home(done) => {
+        self.last_preset = None;
+        let r = self.home().await;
+        self.finish_move(done, r, true, true, true)?;
       },
-      protos::CamCmd::MoveAbsolute(pantilt) => {
-        self.pantilt.absolute_move(&self.cam, pantilt.pan, pantilt.tilt).await?;
+      protos::CamCmd::MoveContinuous(pantilt, done) => {
+        self.wait_move(true, false, false, None);
+        self.pantilt.target = None;
+        self.pantilt.reset = None;
+        if pantilt.pan != 0 || pantilt.tilt != 0 { self.last_preset = None; }
+        let (panspeed, tiltspeed) = self.pantilt.directions(pantilt.pan, pantilt.tilt);
+        self.pantilt.panspeed = panspeed; self.pantilt.tiltspeed = tiltspeed;
+        let r = self.pantilt.periodic_move(&self.cam).await;
+        finish(done, r)?;
+      },
+      protos::CamCmd::MoveRelative(pantilt, speed, done) => {
+        self.last_preset = None;
+        let (pan_move, tilt_move) = self.pantilt.directions(pantilt.pan, pantilt.tilt);
+        let (pan, tilt) = (self.pantilt.pan.value + pan_move, self.pantilt.tilt.value + tilt_move);
+        let r = self.pantilt.move_to(&self.cam, pan, tilt, speed.pan, speed.tilt).await;
+        self.finish_move(done, r, true, false, false)?;
+      },
+      protos::CamCmd::MoveAbsolute(pantilt, speed, done) => {
+        self.last_preset = None;
+        let r = self.pantilt.move_to(&self.cam, pantilt.pan, pantilt.tilt, speed.pan, speed.tilt).await;
+        self.finish_move(done, r, true, false, false)?;
       },
 
 
-      protos::CamCmd::ZoomContinuous(zoom_f64) => { // -1 to 1
-        self.zoom.zoomspeed = (((self.zoom.zoom.maximum-self.zoom.zoom.minimum) as f64)*
-          zoom_f64/20.0) as i64; // ops per 50ms
-        self.zoom.periodic_move(&self.cam).await?;
+      protos::CamCmd::ZoomContinuous(zoom_f64, done) => { // -1 to 1
+        self.wait_move(false, true, false, None);
+        if zoom_f64 != 0.0 { self.last_preset = None; }
+        let r = self.zoom_continuous(zoom_f64).await;
+        finish(done, r)?;
       },
-      protos::CamCmd::ZoomDirect(zoom_f64) => { // 0 to 1.0
-        self.zoom.absolute(&self.cam, self.zoom.zoom.minimum+
-          (((self.zoom.zoom.maximum-self.zoom.zoom.minimum) as f64)*zoom_f64) as i64).await?;
+      protos::CamCmd::ZoomDirect(zoom_f64, done) => { // 0 to 1.0
+        self.last_preset = None;
+        let r = self.zoom_direct(zoom_f64).await;
+        self.finish_move(done, r, false, true, false)?;
       },
-      protos::CamCmd::AutoFocus(active) => {
-        self.focus.absolute(&self.cam, active, self.focus.focus.value).await?;
+      protos::CamCmd::ZoomFocusDirect(zoom_f64, focus_f64, done) => {
+        self.last_preset = None;
+        let r = self.zoom_focus_direct(zoom_f64, focus_f64).await;
+        self.finish_move(done, r, false, true, true)?;
       },
-      protos::CamCmd::AutoFocusToggle() => {
-        let active = if self.focus.auto.value == 0 {true} else {false};
-        self.focus.absolute(&self.cam, active, self.focus.focus.value).await?;
+      protos::CamCmd::DigitalZoom(on, done) => {
+        let r = self.digital_zoom(on).await;
+        finish(done, r)?;
       },
-      protos::CamCmd::FocusContinuous(focus_f64) => { // -1 to 1
-        self.focus.absolute(&self.cam, false, self.focus.focus.value).await?;
-        self.focus.focusspeed = (((self.focus.focus.maximum-self.focus.focus.minimum) as f64)*
-          focus_f64/20.0) as i64; // ops per 50ms
-        self.focus.periodic_move(&self.cam).await?;
+      protos::CamCmd::AutoFocus(active, done) => {
+        let r = self.auto_focus(active).await;
+        finish(done, r)?;
       },
-      protos::CamCmd::FocusDirect(focus_f64) => { // 1.0 (Near) - 0.0 (Far)
-        self.focus.absolute(&self.cam, true, self.focus.focus.minimum+
-          (((self.focus.focus.maximum-self.focus.focus.minimum) as f64)*focus_f64) as i64).await?;
+      protos::CamCmd::AutoFocusToggle(done) => {
+        let r = self.auto_focus_toggle().await;
+        finish(done, r)?;
       },
-      protos::CamCmd::FocusOnePushTrigger() => {
-        // couldn't make it work
+      protos::CamCmd::FocusContinuous(focus_f64, done) => { // -1 to 1
+        self.wait_move(false, false, true, None);
+        let r = self.focus_continuous(focus_f64).await;
+        finish(done, r)?;
       },
-      protos::CamCmd::WhiteBalanceTrigger() => {
-        // couldn't make it work
+      protos::CamCmd::FocusDirect(focus_f64, done) => { // 1.0 (Near) - 0.0 (Far)
+        let r = self.focus_direct(focus_f64).await;
+        self.finish_move(done, r, false, false, true)?;
       },
-      protos::CamCmd::WhiteBalanceMode(wb) => {
-        if wb == 0 {       // 0-Auto
-          self.whitebal.absolute(&self.cam, true, 6500).await?;
-        } else if wb == 1 {       // 1-Indoor
-          self.whitebal.absolute(&self.cam, false, 3200).await?;
-        } else if wb == 2 {       // 2-Outdoor
-          self.whitebal.absolute(&self.cam, false, 5800).await?;
-        }
-        // 3-One Push WB
-        // 4-Auto Tracing
-        // 5-Manual  
+      protos::CamCmd::FocusOnePushTrigger(done) => {
+        self.focus_one_push(done).await?;
+      },
+      protos::CamCmd::AfMode(mode, done) => {
+        let r = self.af_mode(mode).await;
+        finish(done, r)?;
+      },
+      protos::CamCmd::AfSensitivity(low, done) => {
+        let r = self.af_sensitivity(low).await;
+        finish(done, r)?;
+      },
+      protos::CamCmd::AfInterval(active, staying, done) => {
+        let r = avail(&mut self.focus).map(|focus| focus.set_af_interval(active, staying));
+        finish(done, r)?;
+      },
+      protos::CamCmd::WhiteBalanceTrigger(done) => {
+        self.white_balance_trigger(done).await?;
+      },
+      protos::CamCmd::WhiteBalanceMode(wb, done) => {
+        let r = self.white_balance_mode(wb).await;
+        finish(done, r)?;
+      },
+      protos::CamCmd::WhiteBalanceGain(red, blue, done) => {
+        let r = self.white_balance_gain(red, blue).await;
+        finish(done, r)?;
+      },
+      protos::CamCmd::AutoExposure(mode, done) => {
+        let r = self.auto_exposure(mode).await;
+        finish(done, r)?;
+      },
+      protos::CamCmd::Shutter(secs, done) => {
+        let r = self.shutter(secs).await;
+        finish(done, r)?;
+      },
+      protos::CamCmd::Gain(gain_f64, done) => {
+        let r = self.gain(gain_f64).await;
+        finish(done, r)?;
+      },
+      protos::CamCmd::Brightness(bright_f64, done) => {
+        let r = self.brightness(bright_f64).await;
+        finish(done, r)?;
+      },
+      protos::CamCmd::ExposureCompensationOn(on, done) => {
+        let r = self.exposure_compensation(Some(on), None).await;
+        finish(done, r)?;
+      },
+      protos::CamCmd::ExposureCompensation(comp, done) => {
+        let r = self.exposure_compensation(None, Some(comp)).await;
+        finish(done, r)?;
+      },
+      protos::CamCmd::Backlight(on, done) => {
+        let r = self.backlight(on).await;
+        finish(done, r)?;
+      },
+      protos::CamCmd::Sharpness(f, done) => {
+        let r = self.image.direct(&self.cam, uvc::CamControl::Sharpness, f).await;
+        finish(done, r)?;
+      },
+      protos::CamCmd::Saturation(f, done) => {
+        let r = self.image.direct(&self.cam, uvc::CamControl::Saturation, Some(f)).await;
+        finish(done, r)?;
+      },
+      protos::CamCmd::Contrast(f, done) => {
+        let r = self.image.direct(&self.cam, uvc::CamControl::Contrast, Some(f)).await;
+        finish(done, r)?;
+      },
+      protos::CamCmd::Gamma(f, done) => {
+        let r = self.image.direct(&self.cam, uvc::CamControl::Gamma, Some(f)).await;
+        finish(done, r)?;
+      },
+      protos::CamCmd::Flip(h, v, done) => {
+        let r = self.flip(h, v).await;
+        finish(done, r)?;
       },
 
       protos::CamCmd::QueryPanTilt(s) => {
-        s.send(protos::PanTilt {
+        s.send(Ok(protos::PanTilt {
           pan: self.pantilt.pan.value,
           tilt: self.pantilt.tilt.value,
-        }).map_err(|_x| UVIError::AsyncChannelClosed)?;
+        })).ok();
       },
       protos::CamCmd::QueryFocusMode(s) => {
-        s.send(
-          if self.focus.auto.value>0 {true} else {false}
-        ).map_err(|_x| UVIError::AsyncChannelClosed)?;
+        s.send(self.focus_mode()).ok();
+      },
+      protos::CamCmd::QueryPanTiltLimit(corner, s) => {
+        let (pan, tilt) = (&self.pantilt.pan, &self.pantilt.tilt);
+        s.send(Ok(if corner == 0 {
+          protos::PanTilt { pan: pan.low, tilt: tilt.low }
+        } else {
+          protos::PanTilt { pan: pan.high, tilt: tilt.high }
+        })).ok();
+      },
+      protos::CamCmd::QueryFocus(s) => {
+        s.send(self.focus_position().await).ok();
+      },
+      protos::CamCmd::QueryPreset(s) => {
+        s.send(Ok(self.last_preset)).ok();
       },
       protos::CamCmd::QueryWhiteBalanceMode(s) => {
-        s.send(
-          if self.whitebal.auto.value > 0 { 0 }
-          else if self.whitebal.temp.value < 4000 { 1 }
-          else {2}
-        ).map_err(|_x| UVIError::AsyncChannelClosed)?;
+        s.send(self.white_balance_state()).ok();
+      },
+      protos::CamCmd::QueryStatus(s) => {
+        s.send(self.status().await).ok();
       },
 
       /*protos::CamCmd::Close() => {
@@ -387,4 +1515,3 @@ impl AutoCamera {
     Ok(true)
   }
 }
-
@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use dirs;
+use crate::uvierror::UVIError;
+
+// optional settings on config.txt, next to presets.db:
+//   # comment
+//   key = value
+pub struct Config {
+    values: HashMap<String, String>
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+// read on the first use
+pub fn settings() -> &'static Config {
+    CONFIG.get_or_init(Config::load)
+}
+
+// the tests give their settings instead of config.txt, the same for all of them
+#[cfg(test)]
+pub fn use_settings(text: &str) {
+    CONFIG.set(Config { values: parse(text) }).ok();
+}
+
+// file on the configuration directory of the application, created if missing
+pub fn config_file(name: &str) -> Result<PathBuf, UVIError> {
+    let mut path = dirs::config_dir().ok_or(UVIError::BadDirs)?;
+    path.push("webcam-visca-ip");
+    fs::create_dir_all(path.to_str().ok_or(UVIError::BadDirs)?)?;
+    path.push(name);
+    Ok(path)
+}
+
+fn parse(text: &str) -> HashMap<String, String> {
+    let mut values = HashMap::new();
+    for line in text.lines() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() { continue; }
+        match line.split_once('=') {
+            Some((key, value)) => { values.insert(key.trim().to_string(), value.trim().to_string()); },
+            None => eprintln!("config.txt: line without '=': {}", line)
+        }
+    }
+    values
+}
+
+impl Config {
+    fn load() -> Config {
+        // no file: every setting at its default
+        let text = config_file("config.txt").and_then(|path| Ok(fs::read_to_string(path)?));
+        Config { values: text.map(|text| parse(&text)).unwrap_or_default() }
+    }
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(|v| v.as_str())
+    }
+    // key.2 applies only to camera 2 (ncam 1), over the key of all cameras
+    pub fn cam_key(&self, key: &str, ncam: u8) -> String {
+        let kcam = format!("{}.{}", key, ncam+1);
+        if self.get(&kcam).is_some() { kcam } else { key.to_string() }
+    }
+    // decimal, or hexadecimal with 0x like the VISCA documents
+    pub fn get_u32(&self, key: &str) -> Option<u32> {
+        let value = self.get(key)?;
+        let parsed = match value.strip_prefix("0x") {
+            Some(hex) => u32::from_str_radix(hex, 16),
+            None => value.parse()
+        };
+        match parsed {
+            Ok(n) => Some(n),
+            Err(_e) => {
+                eprintln!("config.txt: {} is not a number: {}", key, value);
+                None
+            }
+        }
+    }
+    // hexadecimal bytes separated by spaces: 00 01 05 19
+    pub fn get_bytes(&self, key: &str) -> Option<Vec<u8>> {
+        let value = self.get(key)?;
+        match value.split_whitespace().map(|b| u8::from_str_radix(b, 16)).collect() {
+            Ok(bytes) => Some(bytes),
+            Err(_e) => {
+                eprintln!("config.txt: {} is not a list of hexadecimal bytes: {}", key, value);
+                None
+            }
+        }
+    }
+}
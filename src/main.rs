@@ -3,9 +3,11 @@
 use std::collections::BTreeMap;
 use std::net;
 
+mod config;
 mod presetdb;
 mod protos;
 mod viscaip;
+mod visca;
 mod uvc;
 mod auto_uvc;
 mod uvierror;
@@ -15,8 +17,9 @@ use std::io::ErrorKind;
 mod uvc_linux;
 #[cfg(all(not(feature="uvcmock"), target_os = "windows"))]
 mod uvc_win;
-#[cfg(feature="uvcmock")]
+#[cfg(any(feature="uvcmock", test))] // the tests pick the mock camera
 mod uvc_mock;
+mod uvc_visca;
 
 use iced::{
     window, executor, Alignment, Column, Element, Application, Command, Settings, Text, Length
@@ -31,8 +34,10 @@ use tokio::time::{Duration, Instant, sleep_until};
 pub struct CamAppState {
     ncam: u8,
     port: u32,
+    udpport: Option<u32>,
     bus: String,
     ncnx: i64,
+    tally: bool,
 }
 
 #[derive(Default, Debug)]
@@ -45,10 +50,11 @@ struct WebCamViscaIPApp {
 pub enum Message {
     AdminChannelReady(mpsc::Sender<protos::MainEvent>),
     CamerasReady,
-    NewViscaCam(u8, u32, String),
+    NewViscaCam(u8, u32, Option<u32>, String),
     LostViscaCam(u8),
     NewViscaConnection(u8, net::SocketAddr),
-    LostViscaConnection(u8, net::SocketAddr)
+    LostViscaConnection(u8, net::SocketAddr),
+    Tally(u8, bool)
 }
 
 enum AppSubscrState {
@@ -82,12 +88,14 @@ impl Application for WebCamViscaIPApp {
             Message::CamerasReady => {
                 Command::none()
             },
-            Message::NewViscaCam(ncam, port, bus) => {
+            Message::NewViscaCam(ncam, port, udpport, bus) => {
                 let cam = CamAppState {
                     ncam: ncam,
                     port: port,
+                    udpport: udpport,
                     bus: bus,
-                    ncnx: 0
+                    ncnx: 0,
+                    tally: false
                 };
                 self.cams.insert(ncam, cam);
                 Command::none()
@@ -108,6 +116,12 @@ impl Application for WebCamViscaIPApp {
                 self.cams.remove(&ncam);
                 Command::none()
             },
+            Message::Tally(ncam, on) => {
+                if let Some(cam) = self.cams.get_mut(&ncam) {
+                    cam.tally = on;
+                }
+                Command::none()
+            },
         }
     }
     fn subscription(&self) -> Subscription<Message> {
@@ -123,8 +137,8 @@ impl Application for WebCamViscaIPApp {
                     let ev = receiver.recv().await.expect("can't happen: copy of sender retained");
     
                     match ev {
-                        protos::MainEvent::NewViscaCam(ncam, port, bus) => {
-                            (Some(Message::NewViscaCam(ncam, port, bus)),
+                        protos::MainEvent::NewViscaCam(ncam, port, udpport, bus) => {
+                            (Some(Message::NewViscaCam(ncam, port, udpport, bus)),
                                  AppSubscrState::Ready(receiver))
                         },
                         protos::MainEvent::NewViscaConnection(ncam, addr) => {
@@ -138,6 +152,10 @@ impl Application for WebCamViscaIPApp {
                         protos::MainEvent::LostViscaCam(ncam) => {
                             (Some(Message::LostViscaCam(ncam)),
                                  AppSubscrState::Ready(receiver))
+                        },
+                        protos::MainEvent::Tally(ncam, on) => {
+                            (Some(Message::Tally(ncam, on)),
+                                 AppSubscrState::Ready(receiver))
                         }
                     }
                 }
@@ -152,8 +170,14 @@ impl Application for WebCamViscaIPApp {
             .align_items(Alignment::Start)
             .push(Text::new("List of active VISCA IP WebCams:").size(24));
         for (_ncam, cam) in self.cams.iter() {
+            let udpport = match cam.udpport {
+                Some(udpport) => udpport.to_string(),
+                None => "-".to_string()
+            };
+            let tally = if cam.tally { " / ON AIR" } else { "" };
             col = col.push(Text::new(
-                format!("#{} / VISCA port {} / Bus {}: TCP Conections {}", cam.ncam, cam.port, cam.bus, cam.ncnx)
+                format!("#{} / VISCA port {} / UDP port {} / Bus {}: Conections {}{}", cam.ncam, cam.port, udpport,
+                    cam.bus, cam.ncnx, tally)
             ).size(16));
         }
         col.into()
@@ -190,8 +214,8 @@ impl ActiveCams {
 }
 
 async fn try_to_activate_all_cams(ncams: &mut ActiveCams, send_main_event: &mpsc::Sender<protos::MainEvent>,
-        send_ncamdead: &mpsc::Sender<u8>) -> Result<(),UVIError> {
-    'nextcamdev: for ncamdev in 0..8 {
+        send_ncamdead: &mpsc::Sender<u8>, chain: &viscaip::ViscaChain) -> Result<(),UVIError> {
+    'nextcamdev: for ncamdev in (0..8).chain(uvc_visca::bridge_devices()) {
         if ncams.cam_dev_already_active(ncamdev) { continue 'nextcamdev; }
         let (cam_chan, bus) = match auto_uvc::AutoCamera::find_camera(ncamdev).await {
             Ok(n) => Ok(n),
@@ -212,7 +236,6 @@ async fn try_to_activate_all_cams(ncams: &mut ActiveCams, send_main_event: &mpsc
                     cam_chan.clone(), send_ncamdead.clone()).await {
                 Ok(_) => {
                     ncams.cam_active(ncam, ncamdev);
-                    send_main_event.send(protos::MainEvent::NewViscaCam(ncam, port, bus)).await.ok();
                     break;
                 },
                 Err(UVIError::IoError(e)) if e.kind() == ErrorKind::AddrInUse => (),
@@ -227,6 +250,25 @@ async fn try_to_activate_all_cams(ncams: &mut ActiveCams, send_main_event: &mpsc
                 continue 'nextcamdev;
             }
         }
+        let mut udpport: u32 = 52381 + ncam as u32;
+        let udpport = loop {
+            match viscaip::activate_visca_udp_port(udpport, ncam, send_main_event.clone(),
+                    cam_chan.clone()).await {
+                Ok(_) => break Some(udpport),
+                Err(UVIError::IoError(e)) if e.kind() == ErrorKind::AddrInUse => (),
+                Err(error) => {
+                    eprintln!("Problem opening udp port: {:?}", error);
+                    break None;
+                }
+            }
+            udpport += 1;
+            if udpport >= 52400 {
+                eprintln!("No udp ports available");
+                break None;
+            }
+        };
+        chain.add(ncam, cam_chan.clone());
+        send_main_event.send(protos::MainEvent::NewViscaCam(ncam, port, udpport, bus)).await.ok();
     }
     Ok(())
 }
@@ -234,8 +276,14 @@ async fn try_to_activate_all_cams(ncams: &mut ActiveCams, send_main_event: &mpsc
 async fn continuous_activation_all_cams(send_main_event: mpsc::Sender<protos::MainEvent>) {
     let mut ncams = ActiveCams::new();
     let (send_ncamdead, mut recv_ncamdead) = mpsc::channel(100);
+    let chain = viscaip::ViscaChain::new();
+    if let Some(port) = config::settings().get_u32("shared_port") {
+        if let Err(error) = viscaip::activate_visca_shared_port(port, chain.clone(), send_main_event.clone()).await {
+            eprintln!("Problem opening shared tcp port: {:?}", error);
+        }
+    }
     loop {
-        try_to_activate_all_cams(&mut ncams, &send_main_event, &send_ncamdead).await.unwrap();
+        try_to_activate_all_cams(&mut ncams, &send_main_event, &send_ncamdead, &chain).await.unwrap();
         let until = Instant::now() + Duration::from_millis(3000);
         loop {
             tokio::select! {
@@ -1,8 +1,8 @@
+use std::path::PathBuf;
 use rusqlite::{Connection, Result};
 use crate::auto_uvc;
+use crate::protos;
 use crate::uvierror::UVIError;
-use std::fs;
-use dirs;
 
 #[derive(Debug)]
 pub struct PresetDB {
@@ -18,11 +18,22 @@ pub struct Preset {
 }
 */
 
+#[cfg(not(test))]
+fn preset_db_path() -> Result<PathBuf, UVIError> {
+  crate::config::config_file("presets.db")
+}
+
+// the tests never touch the presets of the user: one file, emptied by each run
+#[cfg(test)]
+fn preset_db_path() -> Result<PathBuf, UVIError> {
+  static EMPTIED: std::sync::Once = std::sync::Once::new();
+  let path = std::env::temp_dir().join("webcam-visca-ip-test.db");
+  EMPTIED.call_once(|| { std::fs::remove_file(&path).ok(); });
+  Ok(path)
+}
+
 fn conn_preset_db() -> Result<Connection, UVIError> {
-  let mut path = dirs::config_dir().ok_or(UVIError::BadDirs)?;
-  path.push("webcam-visca-ip");
-  fs::create_dir_all(path.to_str().ok_or(UVIError::BadDirs)?)?;
-  path.push("presets.db");
+  let path = preset_db_path()?;
   let conn = Connection::open(path.to_str().ok_or(UVIError::BadDirs)?)?;
   Ok(conn)
 }
@@ -38,10 +49,39 @@ pub async fn prepare_preset_db() -> Result<(), UVIError> {
       pan INT, tilt INT, zoom INT,
       focusauto BOOL, focus INT,
       whitebalauto BOOL, temperature INT,
+      sharpness INT, saturation INT, contrast INT, gamma INT,
+      panspeed INT, tiltspeed INT,
       PRIMARY KEY (ncam, preset)
     );"#,
     (),
   )?;
+  // image and speed columns added later, NULL in the old presets
+  let mut stmt = conn.prepare("SELECT name FROM pragma_table_info('Presets');")?;
+  let columns = stmt.query_map((), |row| row.get::<_, String>(0))?
+    .collect::<Result<Vec<String>, rusqlite::Error>>()?;
+  for column in ["sharpness", "saturation", "contrast", "gamma", "panspeed", "tiltspeed"] {
+    if !columns.iter().any(|c| c == column) {
+      conn.execute(&format!("ALTER TABLE Presets ADD COLUMN {} INT;", column), ())?;
+    }
+  }
+  conn.execute(
+    r#"
+    CREATE TABLE IF NOT EXISTS Limits (
+      ncam INT, 
+      corner INT, 
+      pan INT, tilt INT,
+      PRIMARY KEY (ncam, corner)
+    );"#,
+    (),
+  )?;
+  conn.execute(
+    r#"
+    CREATE TABLE IF NOT EXISTS PresetSpeeds (
+      ncam INT PRIMARY KEY, 
+      panspeed INT, tiltspeed INT
+    );"#,
+    (),
+  )?;
   Ok(())
 }
 
@@ -62,17 +102,20 @@ impl PresetDB {
     self.conn.execute(
       "INSERT OR REPLACE INTO Presets (ncam,preset,
         pan, tilt, zoom,
-        focusauto, focus, whitebalauto, temperature) 
-        VALUES (?1,?2,?3,?4,?5,?6,?7,?8,?9);",
+        focusauto, focus, whitebalauto, temperature,
+        sharpness, saturation, contrast, gamma, panspeed, tiltspeed) 
+        VALUES (?1,?2,?3,?4,?5,?6,?7,?8,?9,?10,?11,?12,?13,?14,?15);",
       (&(self.ncam as i64), &(npreset as i64), &p.pan, &p.tilt, &p.zoom,
-        &p.focusauto,&p.focus,&p.whitebalauto,&p.temperature),
+        &p.focusauto,&p.focus,&p.whitebalauto,&p.temperature,
+        &p.sharpness,&p.saturation,&p.contrast,&p.gamma,&p.panspeed,&p.tiltspeed),
     )?;
     Ok(())
   }
   pub fn recover(&self, npreset: u8) -> Result<Option<auto_uvc::Preset>, UVIError> {
     match self.conn.query_row(
       "SELECT pan, tilt, zoom,
-      focusauto, focus, whitebalauto, temperature 
+      focusauto, focus, whitebalauto, temperature,
+      sharpness, saturation, contrast, gamma, panspeed, tiltspeed 
       FROM Presets WHERE ncam=?1 and preset=?2;",
       (&(self.ncam as i64), &(npreset as i64)),
       |row| {
@@ -83,7 +126,13 @@ impl PresetDB {
           focusauto: row.get(3)?,
           focus: row.get(4)?,
           whitebalauto: row.get(5)?,
-          temperature: row.get(6)?
+          temperature: row.get(6)?,
+          sharpness: row.get(7)?,
+          saturation: row.get(8)?,
+          contrast: row.get(9)?,
+          gamma: row.get(10)?,
+          panspeed: row.get(11)?,
+          tiltspeed: row.get(12)?
         })
       }
     ) {
@@ -92,5 +141,49 @@ impl PresetDB {
       Err(e) => Err(UVIError::RusqliteError(e))
     }
   }
+  // pan/tilt limits, corner 0: DownLeft 1: UpRight
+  pub fn set_limit(&self, corner: u8, pan: i64, tilt: i64) -> Result<(), UVIError> {
+    self.conn.execute(
+      "INSERT OR REPLACE INTO Limits (ncam,corner,pan,tilt) VALUES (?1,?2,?3,?4);",
+      (&(self.ncam as i64), &(corner as i64), &pan, &tilt),
+    )?;
+    Ok(())
+  }
+  pub fn clear_limit(&self, corner: u8) -> Result<(), UVIError> {
+    self.conn.execute(
+      "DELETE FROM Limits WHERE ncam=?1 AND corner=?2;",
+      (&(self.ncam as i64), &(corner as i64)),
+    )?;
+    Ok(())
+  }
+  pub fn limit(&self, corner: u8) -> Result<Option<(i64, i64)>, UVIError> {
+    match self.conn.query_row(
+      "SELECT pan, tilt FROM Limits WHERE ncam=?1 and corner=?2;",
+      (&(self.ncam as i64), &(corner as i64)),
+      |row| Ok((row.get(0)?, row.get(1)?))
+    ) {
+      Ok(limit) => Ok(Some(limit)),
+      Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+      Err(e) => Err(UVIError::RusqliteError(e))
+    }
+  }
+  // recall speed of the presets recorded without their own
+  pub fn set_preset_speed(&self, speed: &protos::PanTilt) -> Result<(), UVIError> {
+    self.conn.execute(
+      "INSERT OR REPLACE INTO PresetSpeeds (ncam,panspeed,tiltspeed) VALUES (?1,?2,?3);",
+      (&(self.ncam as i64), &speed.pan, &speed.tilt),
+    )?;
+    Ok(())
+  }
+  pub fn preset_speed(&self) -> Result<Option<protos::PanTilt>, UVIError> {
+    match self.conn.query_row(
+      "SELECT panspeed, tiltspeed FROM PresetSpeeds WHERE ncam=?1;",
+      (&(self.ncam as i64),),
+      |row| Ok(protos::PanTilt { pan: row.get(0)?, tilt: row.get(1)? })
+    ) {
+      Ok(speed) => Ok(Some(speed)),
+      Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+      Err(e) => Err(UVIError::RusqliteError(e))
+    }
+  }
 }
-
@@ -1,12 +1,14 @@
 use std::net;
 use tokio::sync::oneshot;
+use crate::uvierror::UVIError;
 
 #[derive(Debug)]
 pub enum MainEvent {
-  NewViscaCam(u8, u32, String),
+  NewViscaCam(u8, u32, Option<u32>, String),
   NewViscaConnection(u8, net::SocketAddr),
   LostViscaConnection(u8, net::SocketAddr),
-  LostViscaCam(u8)
+  LostViscaCam(u8),
+  Tally(u8, bool) // on air
 }
 
 #[derive(Debug)]
@@ -15,27 +17,108 @@ pub struct PanTilt {
   pub tilt: i64 // seconds of an angle (angle/3600) -30 to 30 degrees
 }
 
+// snapshot of the camera settings, None when the device does not provide it
+#[derive(Debug, Default)]
+pub struct CamStatus {
+  pub tally: bool, // on air
+  pub zoom: Option<f64>,  // 0 to 1.0
+  pub zoom_optical: Option<f64>, // end of the optical zoom, 0 to 1.0, None: no digital zoom
+  pub digital_zoom: Option<bool>,
+  pub focus: Option<f64>, // 1.0 (Near) - 0.0 (Far)
+  pub focus_auto: Option<bool>,
+  pub af_mode: Option<u8>, // VISCA AF mode
+  pub af_low: Option<bool>, // AF sensitivity low
+  pub af_interval: Option<(u8, u8)>, // seconds: AF active, then staying
+  pub wb_mode: Option<u8>, // VISCA white balance mode
+  pub red_gain: Option<f64>,  // 0 to 1.0
+  pub blue_gain: Option<f64>, // 0 to 1.0
+  pub ae_mode: Option<u8>, // VISCA AE mode
+  pub shutter: Option<f64>, // seconds
+  pub gain: Option<f64>,    // 0 to 1.0
+  pub bright: Option<f64>,  // 0 to 1.0
+  pub exp_comp_on: Option<bool>,
+  pub exp_comp: Option<f64>, // -1 to 1
+  pub backlight: Option<bool>,
+  pub hflip: Option<bool>,
+  pub vflip: Option<bool>,
+  pub sharpness: Option<f64>,  // 0 to 1.0 of the range of the control
+  pub saturation: Option<f64>,
+  pub contrast: Option<f64>,
+  pub gamma: Option<f64>,
+}
+
+// answer of the camera to a query or a command
+pub type Answer<T> = oneshot::Sender<Result<T, UVIError>>;
+// resolved by the camera when the command has finished (motion at the target),
+// the client may cancel it meanwhile
+#[derive(Debug)]
+pub struct Completion {
+  done: Answer<()>,
+  cancel: oneshot::Receiver<()>
+}
+
+impl Completion {
+  pub fn new() -> (Completion, oneshot::Receiver<Result<(), UVIError>>, oneshot::Sender<()>) {
+    let (send_done, recv_done) = oneshot::channel();
+    let (send_cancel, recv_cancel) = oneshot::channel();
+    (Completion { done: send_done, cancel: recv_cancel }, recv_done, send_cancel)
+  }
+  pub fn send(self, r: Result<(), UVIError>) {
+    self.done.send(r).ok();
+  }
+  pub fn cancelled(&mut self) -> bool {
+    self.cancel.try_recv().is_ok()
+  }
+}
+
 #[derive(Debug)]
 pub enum CamCmd {
   SetPresetNcam(u8),
-  ResetPreset(u8),
-  RecordPreset(u8),
-  RecoverPreset(u8),
-  Home(),
-  MoveContinuous(PanTilt),
-  MoveRelative(PanTilt),
-  MoveAbsolute(PanTilt),
-  ZoomContinuous(f64), // -1 to 1
-  ZoomDirect(f64), // 0 to 1.0
-  AutoFocus(bool),
-  AutoFocusToggle(),
-  FocusContinuous(f64), // -1 to 1
-  FocusDirect(f64), // 1.0 (Near) - 0.0 (Far)
-  FocusOnePushTrigger(),
-  WhiteBalanceTrigger(),
-  WhiteBalanceMode(u8),
-  QueryPanTilt(oneshot::Sender<PanTilt>),
-  QueryFocusMode(oneshot::Sender<bool>),
-  QueryWhiteBalanceMode(oneshot::Sender<u8>),
+  ResetPreset(u8, Completion),
+  RecordPreset(u8, Option<PanTilt>, Completion), // own recall speed of the preset, else the one of the camera
+  RecoverPreset(u8, Completion),
+  Tally(bool, Completion),
+  PresetSpeed(PanTilt, Completion), // recall speed of the camera, saved (seconds of an angle per second)
+  Home(Completion),
+  PanTiltReset(Completion), // recalibration of the motors
+  PanTiltLimitSet(u8, PanTilt, Completion), // corner 0: DownLeft 1: UpRight
+  PanTiltLimitClear(u8, Completion),
+  MoveContinuous(PanTilt, Completion),
+  MoveRelative(PanTilt, PanTilt, Completion), // move, speed (seconds of an angle per second)
+  MoveAbsolute(PanTilt, PanTilt, Completion), // target, speed (seconds of an angle per second)
+  ZoomContinuous(f64, Completion), // -1 to 1
+  ZoomDirect(f64, Completion), // 0 to 1.0
+  ZoomFocusDirect(f64, f64, Completion), // both at once, as ZoomDirect and FocusDirect
+  DigitalZoom(bool, Completion),
+  AutoFocus(bool, Completion),
+  AutoFocusToggle(Completion),
+  FocusContinuous(f64, Completion), // -1 to 1
+  FocusDirect(f64, Completion), // 1.0 (Near) - 0.0 (Far)
+  FocusOnePushTrigger(Completion),
+  AfMode(u8, Completion), // VISCA AF mode
+  AfSensitivity(bool, Completion), // low
+  AfInterval(u8, u8, Completion), // seconds: AF active, then staying
+  WhiteBalanceTrigger(Completion),
+  WhiteBalanceMode(u8, Completion),
+  WhiteBalanceGain(Option<f64>, Option<f64>, Completion), // red, blue 0 to 1.0, None: unchanged
+  AutoExposure(u8, Completion), // VISCA AE mode
+  Shutter(Option<f64>, Completion), // seconds, None: default of the camera
+  Gain(Option<f64>, Completion), // 0 to 1.0, None: default of the camera
+  Brightness(Option<f64>, Completion), // 0 to 1.0, None: default of the camera
+  ExposureCompensationOn(bool, Completion),
+  ExposureCompensation(f64, Completion), // -1 to 1
+  Backlight(bool, Completion),
+  Flip(Option<bool>, Option<bool>, Completion), // horizontal, vertical, None: unchanged
+  Sharpness(Option<f64>, Completion), // 0 to 1.0, None: default of the camera
+  Saturation(f64, Completion), // 0 to 1.0
+  Contrast(f64, Completion), // 0 to 1.0
+  Gamma(f64, Completion), // 0 to 1.0
+  QueryPanTilt(Answer<PanTilt>),
+  QueryPanTiltLimit(u8, Answer<PanTilt>),
+  QueryFocusMode(Answer<bool>),
+  QueryFocus(Answer<f64>), // 1.0 (Near) - 0.0 (Far)
+  QueryWhiteBalanceMode(Answer<u8>),
+  QueryPreset(Answer<Option<u8>>), // last recalled or recorded, None: moved away
+  QueryStatus(Answer<CamStatus>),
   //Close()
 }
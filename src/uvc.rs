@@ -7,11 +7,14 @@ use crate::uvc_linux as uvci;
 use crate::uvc_win as uvci;
 #[cfg(feature="uvcmock")]
 use crate::uvc_mock as uvci;
+use crate::uvc_visca;
 
 #[derive(Debug,Clone)]
 pub enum ControlType {
     Integer,
     Boolean,
+    Menu,
+    Button, // only set, any value
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -23,6 +26,22 @@ pub enum CamControl {
     FocusAuto,
     WhiteBalanceTemperature,
     WhiteBalanceTemperatureAuto,
+    ExposureAuto, // menu: 1 manual, 3 aperture priority
+    ExposureAbsolute, // 100 µs units
+    Gain,
+    Brightness,
+    BacklightCompensation,
+    HorizontalFlip,
+    VerticalFlip,
+    Sharpness,
+    Saturation,
+    Contrast,
+    Gamma,
+    RedBalance,
+    BlueBalance,
+    PanReset,
+    TiltReset,
+    Led1Mode, // Logitech menu: 0 off, 1 on, 2 blinking, 3 auto
 }
 
 impl fmt::Display for CamControl {
@@ -35,6 +54,22 @@ impl fmt::Display for CamControl {
             CamControl::FocusAuto => write!(f, "focus_automatic_continuous"),
             CamControl::WhiteBalanceTemperature => write!(f, "white_balance_temperature"),
             CamControl::WhiteBalanceTemperatureAuto => write!(f, "white_balance_automatic"),
+            CamControl::ExposureAuto => write!(f, "auto_exposure"),
+            CamControl::ExposureAbsolute => write!(f, "exposure_time_absolute"),
+            CamControl::Gain => write!(f, "gain"),
+            CamControl::Brightness => write!(f, "brightness"),
+            CamControl::BacklightCompensation => write!(f, "backlight_compensation"),
+            CamControl::HorizontalFlip => write!(f, "horizontal_flip"),
+            CamControl::VerticalFlip => write!(f, "vertical_flip"),
+            CamControl::Sharpness => write!(f, "sharpness"),
+            CamControl::Saturation => write!(f, "saturation"),
+            CamControl::Contrast => write!(f, "contrast"),
+            CamControl::Gamma => write!(f, "gamma"),
+            CamControl::RedBalance => write!(f, "red_balance"),
+            CamControl::BlueBalance => write!(f, "blue_balance"),
+            CamControl::PanReset => write!(f, "pan_reset"),
+            CamControl::TiltReset => write!(f, "tilt_reset"),
+            CamControl::Led1Mode => write!(f, "led1_mode"),
         }
     }
 }
@@ -92,10 +127,35 @@ impl Camera {
     }
 }
 
+// webcams of the system, or bridges to network cameras from FIRST_BRIDGE on
 pub async fn find_camera(ncam: u8) -> Result<Camera, UVIError> {
+    if let Some(address) = uvc_visca::bridge_address(ncam) {
+        return find_bridge_camera(ncam, address).await;
+    }
     let (send_find, recv_find) = oneshot::channel();
     let (send_cmd, recv_cmd) = mpsc::channel(100);
     uvci::run_handler(ncam, send_find, recv_cmd);
+    found_camera(ncam, recv_find, send_cmd).await
+}
+
+pub async fn find_bridge_camera(ncam: u8, address: String) -> Result<Camera, UVIError> {
+    let (send_find, recv_find) = oneshot::channel();
+    let (send_cmd, recv_cmd) = mpsc::channel(100);
+    uvc_visca::run_handler(address, send_find, recv_cmd);
+    found_camera(ncam, recv_find, send_cmd).await
+}
+
+// the mock camera whatever the backend of the build
+#[cfg(test)]
+pub async fn find_mock_camera(ncam: u8) -> Result<Camera, UVIError> {
+    let (send_find, recv_find) = oneshot::channel();
+    let (send_cmd, recv_cmd) = mpsc::channel(100);
+    crate::uvc_mock::run_handler(ncam, send_find, recv_cmd);
+    found_camera(ncam, recv_find, send_cmd).await
+}
+
+async fn found_camera(ncam: u8, recv_find: oneshot::Receiver<Result<(String,String),UVIError>>,
+        send_cmd: mpsc::Sender<UVCCmd>) -> Result<Camera, UVIError> {
     let (card, bus) = recv_find.await.map_err(|_x| UVIError::AsyncChannelNoSender)??;
     Ok(Camera {
         channel: send_cmd,
@@ -103,4 +163,4 @@ pub async fn find_camera(ncam: u8) -> Result<Camera, UVIError> {
         card: card,
         bus: bus,
     })
-}
\ No newline at end of file
+}
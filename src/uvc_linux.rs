@@ -24,11 +24,27 @@ lazy_static! {
         let mut m = HashMap::new();
         m.insert(0x009a0908, CamControl::PanAbsolute);
         m.insert(0x009a0909, CamControl::TiltAbsolute);
+        m.insert(0x009a0906, CamControl::PanReset);
+        m.insert(0x009a0907, CamControl::TiltReset);
         m.insert(0x009a090d, CamControl::ZoomAbsolute);
         m.insert(0x009a090a, CamControl::FocusAbsolute);
         m.insert(0x009a090c, CamControl::FocusAuto);
         m.insert(0x0098091a, CamControl::WhiteBalanceTemperature);
         m.insert(0x0098090c, CamControl::WhiteBalanceTemperatureAuto);
+        m.insert(0x009a0901, CamControl::ExposureAuto);
+        m.insert(0x009a0902, CamControl::ExposureAbsolute);
+        m.insert(0x00980913, CamControl::Gain);
+        m.insert(0x00980900, CamControl::Brightness);
+        m.insert(0x0098091c, CamControl::BacklightCompensation);
+        m.insert(0x00980914, CamControl::HorizontalFlip);
+        m.insert(0x00980915, CamControl::VerticalFlip);
+        m.insert(0x0098091b, CamControl::Sharpness);
+        m.insert(0x00980902, CamControl::Saturation);
+        m.insert(0x00980901, CamControl::Contrast);
+        m.insert(0x00980910, CamControl::Gamma);
+        m.insert(0x0098090e, CamControl::RedBalance);
+        m.insert(0x0098090f, CamControl::BlueBalance);
+        m.insert(0x0a046d05, CamControl::Led1Mode); // Logitech, mapped by uvcdynctrl
         m
     };
 }
@@ -50,7 +66,9 @@ pub async fn find_camera(ncam: u8) -> Result<(CamInterno,String,String), UVIErro
             let typ = match control.typ {
                 control::Type::Integer => ControlType::Integer,
                 control::Type::Boolean => ControlType::Boolean,
-                _ => break
+                control::Type::Menu => ControlType::Menu,
+                control::Type::Button => ControlType::Button,
+                _ => continue
             };
             let descr = DescriptionInt {
                 id: control.id,
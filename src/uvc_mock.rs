@@ -32,6 +32,15 @@ pub fn mock_find_camera(ncam: u8) -> Result<(CamInterno, String, String), UVIErr
         step: 1,
         default: 0,
     });
+    for camctrl in [CamControl::PanReset, CamControl::TiltReset] {
+        ctrls.insert(camctrl, Description {
+            typ: ControlType::Button,
+            minimum: 0,
+            maximum: 0,
+            step: 0,
+            default: 0,
+        });
+    }
     ctrls.insert(CamControl::ZoomAbsolute, Description {
         typ: ControlType::Integer,
         minimum: 0,
@@ -67,6 +76,90 @@ pub fn mock_find_camera(ncam: u8) -> Result<(CamInterno, String, String), UVIErr
         step: 1,
         default: 1,
     });
+    ctrls.insert(CamControl::ExposureAuto, Description {
+        typ: ControlType::Menu,
+        minimum: 0,
+        maximum: 3,
+        step: 1,
+        default: 3,
+    });
+    ctrls.insert(CamControl::Led1Mode, Description {
+        typ: ControlType::Menu,
+        minimum: 0,
+        maximum: 3,
+        step: 1,
+        default: 3,
+    });
+    ctrls.insert(CamControl::ExposureAbsolute, Description {
+        typ: ControlType::Integer,
+        minimum: 3,
+        maximum: 2047,
+        step: 1,
+        default: 250,
+    });
+    ctrls.insert(CamControl::Gain, Description {
+        typ: ControlType::Integer,
+        minimum: 0,
+        maximum: 255,
+        step: 1,
+        default: 0,
+    });
+    ctrls.insert(CamControl::Brightness, Description {
+        typ: ControlType::Integer,
+        minimum: 0,
+        maximum: 255,
+        step: 1,
+        default: 128,
+    });
+    ctrls.insert(CamControl::BacklightCompensation, Description {
+        typ: ControlType::Integer,
+        minimum: 0,
+        maximum: 1,
+        step: 1,
+        default: 0,
+    });
+    ctrls.insert(CamControl::HorizontalFlip, Description {
+        typ: ControlType::Boolean,
+        minimum: 0,
+        maximum: 1,
+        step: 1,
+        default: 0,
+    });
+    ctrls.insert(CamControl::VerticalFlip, Description {
+        typ: ControlType::Boolean,
+        minimum: 0,
+        maximum: 1,
+        step: 1,
+        default: 0,
+    });
+    ctrls.insert(CamControl::Sharpness, Description {
+        typ: ControlType::Integer,
+        minimum: 0,
+        maximum: 255,
+        step: 1,
+        default: 128,
+    });
+    ctrls.insert(CamControl::Saturation, Description {
+        typ: ControlType::Integer,
+        minimum: 0,
+        maximum: 255,
+        step: 1,
+        default: 128,
+    });
+    ctrls.insert(CamControl::Contrast, Description {
+        typ: ControlType::Integer,
+        minimum: 0,
+        maximum: 255,
+        step: 1,
+        default: 128,
+    });
+    ctrls.insert(CamControl::Gamma, Description {
+        typ: ControlType::Integer,
+        minimum: 100,
+        maximum: 300,
+        step: 1,
+        default: 220,
+    });
     let cam = CamInterno {
         ctrls,
         memory: HashMap::new(),
@@ -82,13 +175,18 @@ impl CamInterno {
     pub fn set_ctrl(&mut self, camctrl: CamControl, vl: i64) -> Result<(), UVIError> {
         let ctrldescr = self.get_ctrl_descr(camctrl)?;
         match ctrldescr.typ {
-            ControlType::Integer => {
+            ControlType::Integer | ControlType::Menu => {
                 self.memory.insert(camctrl, vl as i32);
                 self.changed = true;
             },
             ControlType::Boolean => {
                 self.memory.insert(camctrl, if vl != 0 { 1 } else { 0 });
                 self.changed = true;
+            },
+            ControlType::Button => { // the motor back to the centre
+                let axis = if camctrl == CamControl::PanReset { CamControl::PanAbsolute } else { CamControl::TiltAbsolute };
+                self.memory.insert(axis, 0);
+                self.changed = true;
             }
         }
         Ok(())
@@ -97,12 +195,13 @@ impl CamInterno {
         let ctrldescr = self.get_ctrl_descr(camctrl)?;
         let vl = *self.memory.get(&camctrl).unwrap_or(&0) as i64;
         match ctrldescr.typ {
-            ControlType::Integer => {
+            ControlType::Integer | ControlType::Menu => {
                 Ok(vl)
             },
             ControlType::Boolean => {
                 Ok(vl)
-            }
+            },
+            ControlType::Button => Err(UVIError::CamControlNotFound)
         }
     } 
 
@@ -0,0 +1,380 @@
+use crate::uvierror::UVIError;
+use crate::uvc::{Description, CamControl, ControlType, UVCCmd};
+use crate::visca::{self, ViscaMessage, ViscaCommand, ViscaInquiry, ViscaReply, ViscaErrorCode, FocusMode};
+use crate::viscaip::{sec_angle_to_visca, visca_to_sec_angle};
+use crate::config;
+use std::io;
+use std::ops::Range;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc,oneshot};
+use tokio::task;
+use tokio::time::{timeout, Duration};
+
+/* Bridge: a network PTZ camera, driven by VISCA over TCP, seen as one more device
+  config.txt: bridges = 192.168.0.20:5678, 192.168.0.21:5678
+  Positions go through in the VISCA units of the upstream camera, which keeps its own limits.
+*/
+
+// device numbers of the bridges, after the webcams
+pub const FIRST_BRIDGE: u8 = 64;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+const REPLY_TIMEOUT: Duration = Duration::from_secs(1);
+const DEFAULT_MAX_SPEED: (u8, u8) = (0x18, 0x14);
+
+fn bridges() -> Vec<String> {
+    match config::settings().get("bridges") {
+        Some(list) => list.split(',').map(|a| a.trim()).filter(|a| !a.is_empty()).map(|a| a.to_string()).collect(),
+        None => Vec::new()
+    }
+}
+
+pub fn bridge_devices() -> Range<u8> {
+    FIRST_BRIDGE..FIRST_BRIDGE + bridges().len().min(32) as u8
+}
+
+pub fn bridge_address(ndev: u8) -> Option<String> {
+    bridges().into_iter().nth(ndev.checked_sub(FIRST_BRIDGE)? as usize)
+}
+
+pub struct CamInterno {
+    stream: TcpStream,
+    received: Vec<u8>, // bytes after the last reply
+    busy: [bool; 16], // sockets acknowledged, completion still to come
+    max_speed: (u8, u8),
+    pan: i64, // last position sent, VISCA units
+    tilt: i64,
+}
+
+fn description(camctrl: CamControl) -> Result<Description, UVIError> {
+    let (typ, minimum, maximum, default) = match camctrl {
+        // the whole range of 4 nibbles
+        CamControl::PanAbsolute | CamControl::TiltAbsolute =>
+            (ControlType::Integer, visca_to_sec_angle(-0x7FFF), visca_to_sec_angle(0x7FFF), 0),
+        CamControl::ZoomAbsolute => (ControlType::Integer, 0, 0x4000, 0),
+        CamControl::FocusAbsolute => (ControlType::Integer, 0, 0xF000, 0),
+        CamControl::FocusAuto => (ControlType::Boolean, 0, 1, 1),
+        CamControl::PanReset | CamControl::TiltReset => (ControlType::Button, 0, 0, 0),
+        _ => return Err(UVIError::CamControlNotFound)
+    };
+    Ok(Description { typ: typ, minimum: minimum, maximum: maximum, step: 1, default: default })
+}
+
+fn reply_error(code: ViscaErrorCode) -> UVIError {
+    match code {
+        ViscaErrorCode::Cancelled => UVIError::CommandCancelled,
+        _ => UVIError::CommandNotSupported
+    }
+}
+
+// inquiry data made only of nibbles, at least n
+fn nibbles(data: Vec<u8>, n: usize) -> Result<Vec<u8>, UVIError> {
+    if data.len() < n || data.iter().any(|b| *b > 0xF) {
+        return Err(UVIError::CommandNotSupported);
+    }
+    Ok(data)
+}
+
+pub async fn find_camera(address: &str) -> Result<(CamInterno,String,String), UVIError> {
+    let stream = timeout(CONNECT_TIMEOUT, TcpStream::connect(address)).await
+        .map_err(|_e| io::Error::from(io::ErrorKind::TimedOut))??;
+    stream.set_nodelay(true)?;
+    let mut cam = CamInterno {
+        stream: stream,
+        received: Vec::new(),
+        busy: [false; 16],
+        max_speed: DEFAULT_MAX_SPEED,
+        pan: 0,
+        tilt: 0,
+    };
+    // something answering VISCA, with pan and tilt
+    let (pan, tilt) = match cam.pan_tilt().await {
+        Ok(pt) => pt,
+        Err(UVIError::IoError(e)) => return Err(UVIError::IoError(e)),
+        Err(_e) => return Err(UVIError::CameraNotFound)
+    };
+    cam.pan = pan;
+    cam.tilt = tilt;
+    if let Ok(data) = cam.inquiry(ViscaInquiry::PanTiltMaxSpeed()).await {
+        if data.len() == 2 && data[0] > 0 && data[1] > 0 {
+            cam.max_speed = (data[0], data[1]);
+        }
+    }
+    Ok((cam, "VISCA bridge".to_string(), address.to_string()))
+}
+
+impl CamInterno {
+    async fn send(&mut self, msg: ViscaMessage) -> Result<(), UVIError> {
+        let pkt = visca::encode(visca::header(0, 1), &msg);
+        self.stream.write_all(&pkt).await?;
+        Ok(())
+    }
+    async fn next_reply(&mut self) -> Result<ViscaReply, UVIError> {
+        loop {
+            if let Some(end) = self.received.iter().position(|b| *b == 0xFF) {
+                let pkt: Vec<u8> = self.received.drain(..=end).collect();
+                match visca::decode_reply(&pkt) {
+                    Ok((_hdr, reply)) => return Ok(reply),
+                    Err(e) => {
+                        eprintln!("VISCA bridge: {}: {:02X?}", e, pkt);
+                        continue;
+                    }
+                }
+            }
+            let mut buf = [0u8; 64];
+            let n = timeout(REPLY_TIMEOUT, self.stream.read(&mut buf)).await
+                .map_err(|_e| io::Error::from(io::ErrorKind::TimedOut))??;
+            if n == 0 {
+                return Err(UVIError::IoError(io::Error::from(io::ErrorKind::UnexpectedEof)));
+            }
+            self.received.extend(&buf[..n]);
+        }
+    }
+    // replies to earlier commands, true when consumed
+    fn late_reply(&mut self, reply: &ViscaReply) -> bool {
+        match *reply {
+            ViscaReply::Completion(socket) | ViscaReply::Error(socket, _) if self.busy[socket as usize] => {
+                self.busy[socket as usize] = false;
+                true
+            },
+            ViscaReply::Completion(_) => true,
+            _ => false
+        }
+    }
+    // acknowledged is enough: the moves are steps of the motion loop
+    async fn command(&mut self, cmd: ViscaCommand) -> Result<(), UVIError> {
+        self.send(ViscaMessage::Command(cmd)).await?;
+        loop {
+            let reply = self.next_reply().await?;
+            if self.late_reply(&reply) { continue; }
+            match reply {
+                ViscaReply::Ack(socket) => {
+                    self.busy[socket as usize] = true;
+                    return Ok(());
+                },
+                ViscaReply::Error(_socket, code) => return Err(reply_error(code)),
+                _ => ()
+            }
+        }
+    }
+    async fn inquiry(&mut self, inq: ViscaInquiry) -> Result<Vec<u8>, UVIError> {
+        self.send(ViscaMessage::Inquiry(inq)).await?;
+        loop {
+            let reply = self.next_reply().await?;
+            if self.late_reply(&reply) { continue; }
+            match reply {
+                ViscaReply::InquiryData(data) => return Ok(data),
+                ViscaReply::Error(_socket, code) => return Err(reply_error(code)),
+                _ => ()
+            }
+        }
+    }
+    // pan of 4 or 5 nibbles, then tilt
+    async fn pan_tilt(&mut self) -> Result<(i64, i64), UVIError> {
+        let data = nibbles(self.inquiry(ViscaInquiry::PanTiltPos()).await?, 8)?;
+        let split = data.len() - data.len()/2;
+        Ok((visca::nibbles_to_int(&data[..split]), visca::nibbles_to_int(&data[split..])))
+    }
+    // the other axis stays where it was asked, once the camera took the move
+    async fn move_to(&mut self, pan: i64, tilt: i64) -> Result<(), UVIError> {
+        self.command(ViscaCommand::PanTiltAbsolute {
+            pan_speed: self.max_speed.0, tilt_speed: self.max_speed.1, pan: pan, tilt: tilt
+        }).await?;
+        self.pan = pan;
+        self.tilt = tilt;
+        Ok(())
+    }
+    pub async fn set_ctrl(&mut self, camctrl: CamControl, vl: i64) -> Result<(), UVIError> {
+        description(camctrl)?;
+        match camctrl {
+            CamControl::PanAbsolute => self.move_to(sec_angle_to_visca(vl), self.tilt).await,
+            CamControl::TiltAbsolute => self.move_to(self.pan, sec_angle_to_visca(vl)).await,
+            CamControl::ZoomAbsolute => self.command(ViscaCommand::ZoomDirect(vl as u16)).await,
+            CamControl::FocusAbsolute => self.command(ViscaCommand::FocusDirect(vl as u16)).await,
+            CamControl::FocusAuto => self.command(ViscaCommand::FocusMode(
+                if vl != 0 { FocusMode::Auto } else { FocusMode::Manual })).await,
+            CamControl::PanReset => self.command(ViscaCommand::PanTiltReset()).await,
+            CamControl::TiltReset => Ok(()), // the same VISCA reset, already sent for the pan
+            _ => Err(UVIError::CamControlNotFound)
+        }
+    }
+    pub async fn get_ctrl(&mut self, camctrl: CamControl) -> Result<i64, UVIError> {
+        if let ControlType::Button = description(camctrl)?.typ {
+            return Err(UVIError::CamControlNotFound);
+        }
+        match camctrl {
+            CamControl::PanAbsolute => Ok(visca_to_sec_angle(self.pan_tilt().await?.0)),
+            CamControl::TiltAbsolute => Ok(visca_to_sec_angle(self.pan_tilt().await?.1)),
+            CamControl::ZoomAbsolute => {
+                let data = nibbles(self.inquiry(ViscaInquiry::ZoomPos()).await?, 4)?;
+                Ok(visca::nibbles_to_uint(&data[..4]))
+            },
+            CamControl::FocusAbsolute => {
+                let data = nibbles(self.inquiry(ViscaInquiry::FocusPos()).await?, 4)?;
+                Ok(visca::nibbles_to_uint(&data[..4]))
+            },
+            CamControl::FocusAuto => {
+                let data = nibbles(self.inquiry(ViscaInquiry::FocusMode()).await?, 1)?;
+                Ok(if data[0] == 0x02 { 1 } else { 0 })
+            },
+            _ => Err(UVIError::CamControlNotFound)
+        }
+    }
+
+    pub async fn run_command(&mut self, ev: UVCCmd) {
+        match ev {
+            UVCCmd::GetCtrlDescr(camctrl, s) => {
+                s.send(description(camctrl)).ok();
+            },
+            UVCCmd::SetCtrl(camctrl, vl, s) => {
+                s.send(self.set_ctrl(camctrl, vl).await).ok();
+            },
+            UVCCmd::GetCtrl(camctrl, s) => {
+                s.send(self.get_ctrl(camctrl).await).ok();
+            }
+        }
+    }
+}
+
+pub fn run_handler(address: String, send_find: oneshot::Sender<Result<(String,String),UVIError>>,
+        mut recv_cmd: mpsc::Receiver<UVCCmd>) {
+    task::spawn(async move {
+        match find_camera(&address).await {
+            Err(e) => {
+                send_find.send(Err(e)).ok();
+            },
+            Ok((mut cam, card, bus)) => {
+                send_find.send(Ok((card, bus))).ok();
+                while let Some(ev) = recv_cmd.recv().await {
+                    cam.run_command(ev).await;
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uvc;
+    use crate::protos;
+    use crate::auto_uvc::AutoCamera;
+    use std::sync::{Arc, Mutex};
+    use tokio::net::TcpListener;
+
+    // what the stand-in camera was driven to, VISCA units
+    #[derive(Debug, Default)]
+    struct Upstream {
+        pan: i64,
+        tilt: i64,
+        zoom: u16,
+        focus: u16,
+        manual_focus: bool,
+        resets: u32,
+    }
+
+    // commands take effect at once, the completion follows the ACK
+    fn answer(up: &mut Upstream, pkt: &[u8]) -> Vec<ViscaReply> {
+        let syntax_error = vec![ViscaReply::Error(0, ViscaErrorCode::Syntax)];
+        match visca::decode(pkt) {
+            Ok((_hdr, ViscaMessage::Command(cmd))) => {
+                match cmd {
+                    ViscaCommand::PanTiltAbsolute { pan, tilt, .. } => { up.pan = pan; up.tilt = tilt; },
+                    ViscaCommand::PanTiltReset() => { up.pan = 0; up.tilt = 0; up.resets += 1; },
+                    ViscaCommand::ZoomDirect(zoom) => up.zoom = zoom,
+                    ViscaCommand::FocusDirect(focus) => up.focus = focus,
+                    ViscaCommand::FocusMode(mode) => up.manual_focus = mode == FocusMode::Manual,
+                    _ => return syntax_error
+                }
+                vec![ViscaReply::Ack(1), ViscaReply::Completion(1)]
+            },
+            Ok((_hdr, ViscaMessage::Inquiry(inq))) => {
+                let data = match inq {
+                    ViscaInquiry::PanTiltPos() => [visca::int_to_nibbles(up.pan, 5), visca::int_to_nibbles(up.tilt, 4)].concat(),
+                    ViscaInquiry::ZoomPos() => visca::int_to_nibbles(up.zoom as i64, 4),
+                    ViscaInquiry::FocusPos() => visca::int_to_nibbles(up.focus as i64, 4),
+                    ViscaInquiry::FocusMode() => vec![if up.manual_focus { 0x03 } else { 0x02 }],
+                    _ => return syntax_error
+                };
+                vec![ViscaReply::InquiryData(data)]
+            },
+            _ => syntax_error
+        }
+    }
+
+    // a network camera on a local port, for one connection
+    async fn stand_in() -> (String, Arc<Mutex<Upstream>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let state = Arc::new(Mutex::new(Upstream::default()));
+        let up = state.clone();
+        task::spawn(async move {
+            let (mut stream, _addr) = listener.accept().await.unwrap();
+            let mut received = Vec::new();
+            let mut buf = [0u8; 64];
+            loop {
+                let n = stream.read(&mut buf).await.unwrap();
+                if n == 0 { break; }
+                received.extend(&buf[..n]);
+                while let Some(end) = received.iter().position(|b| *b == 0xFF) {
+                    let pkt: Vec<u8> = received.drain(..=end).collect();
+                    let replies = answer(&mut up.lock().unwrap(), &pkt);
+                    for reply in replies {
+                        stream.write_all(&visca::encode_reply(0x90, &reply)).await.unwrap();
+                    }
+                }
+            }
+        });
+        (address, state)
+    }
+
+    #[tokio::test]
+    async fn controls() {
+        let (address, up) = stand_in().await;
+        let cam = uvc::find_bridge_camera(FIRST_BRIDGE, address.clone()).await.unwrap();
+        assert_eq!(cam.bus, address);
+        cam.set_ctrl(CamControl::PanAbsolute, visca_to_sec_angle(-0x123)).await.unwrap();
+        cam.set_ctrl(CamControl::TiltAbsolute, visca_to_sec_angle(0x45)).await.unwrap();
+        let pos = up.lock().map(|up| (up.pan, up.tilt)).unwrap();
+        assert_eq!(pos, (-0x123, 0x45));
+        assert_eq!(cam.get_ctrl(CamControl::PanAbsolute).await.unwrap(), visca_to_sec_angle(-0x123));
+        assert_eq!(cam.get_ctrl(CamControl::TiltAbsolute).await.unwrap(), visca_to_sec_angle(0x45));
+        cam.set_ctrl(CamControl::ZoomAbsolute, 0x2000).await.unwrap();
+        assert_eq!(cam.get_ctrl(CamControl::ZoomAbsolute).await.unwrap(), 0x2000);
+        cam.set_ctrl(CamControl::FocusAuto, 0).await.unwrap();
+        cam.set_ctrl(CamControl::FocusAbsolute, 0x1234).await.unwrap();
+        assert_eq!(cam.get_ctrl(CamControl::FocusAuto).await.unwrap(), 0);
+        assert_eq!(cam.get_ctrl(CamControl::FocusAbsolute).await.unwrap(), 0x1234);
+        cam.set_ctrl(CamControl::PanReset, 1).await.unwrap();
+        cam.set_ctrl(CamControl::TiltReset, 1).await.unwrap();
+        assert_eq!(up.lock().unwrap().resets, 1);
+        assert!(matches!(cam.get_ctrl_descr(CamControl::Gain).await, Err(UVIError::CamControlNotFound)));
+    }
+
+    #[tokio::test]
+    async fn nobody_listening() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        drop(listener);
+        assert!(matches!(uvc::find_bridge_camera(FIRST_BRIDGE, address).await, Err(UVIError::IoError(_))));
+    }
+
+    // the motion loop of the webcams, unchanged, on the network camera
+    #[tokio::test]
+    async fn auto_camera() {
+        let (address, up) = stand_in().await;
+        let cam = uvc::find_bridge_camera(FIRST_BRIDGE, address).await.unwrap();
+        let (cam_chan, _bus) = AutoCamera::start(cam).await.unwrap();
+        let (done, recv_done, _cancel) = protos::Completion::new();
+        cam_chan.send(protos::CamCmd::MoveAbsolute(
+            protos::PanTilt { pan: visca_to_sec_angle(0x200), tilt: visca_to_sec_angle(-0x100) },
+            protos::PanTilt { pan: 345600, tilt: 72000 }, done)).unwrap();
+        timeout(Duration::from_secs(10), recv_done).await.unwrap().unwrap().unwrap();
+        let pos = up.lock().map(|up| (up.pan, up.tilt)).unwrap();
+        assert_eq!(pos, (0x200, -0x100));
+        let (answer, recv_answer) = oneshot::channel();
+        cam_chan.send(protos::CamCmd::QueryPanTilt(answer)).unwrap();
+        let pos = recv_answer.await.unwrap().unwrap();
+        assert_eq!((pos.pan, pos.tilt), (visca_to_sec_angle(0x200), visca_to_sec_angle(-0x100)));
+    }
+}
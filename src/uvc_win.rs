@@ -114,8 +114,124 @@ pub fn find_camera(ncam: u8) -> Result<(CamInterno, String, String), UVIError> {
                     }
                 });
             },
-            //Brightness,Contrast,Hue,Saturation,Sharpness,Gamma,ColorEnable,
-            //BacklightComp,Gain,Roll,Exposure,Iris,
+            Exposure => {
+                ctrls.insert(CamControl::ExposureAbsolute, DescriptionInt {
+                    kcontrol: *kcontrol,
+                    sec2degree: false,
+                    descr: Description {
+                        typ: ControlType::Integer,
+                        minimum: control.minimum_value().into(),
+                        maximum: control.maximum_value().into(),
+                        step: control.step() as u64,
+                        default: control.default().into(),
+                    }
+                });
+                // the same control, its flag is the menu: 1 manual, 3 automatic, the
+                // values of auto_exposure on linux (not tested on Windows)
+                ctrls.insert(CamControl::ExposureAuto, DescriptionInt {
+                    kcontrol: *kcontrol,
+                    sec2degree: false,
+                    descr: Description {
+                        typ: ControlType::Menu,
+                        minimum: 1,
+                        maximum: 3,
+                        step: 1,
+                        default: 3,
+                    }
+                });
+            },
+            Gain => {
+                ctrls.insert(CamControl::Gain, DescriptionInt {
+                    kcontrol: *kcontrol,
+                    sec2degree: false,
+                    descr: Description {
+                        typ: ControlType::Integer,
+                        minimum: control.minimum_value().into(),
+                        maximum: control.maximum_value().into(),
+                        step: control.step() as u64,
+                        default: control.default().into(),
+                    }
+                });
+            },
+            Brightness => {
+                ctrls.insert(CamControl::Brightness, DescriptionInt {
+                    kcontrol: *kcontrol,
+                    sec2degree: false,
+                    descr: Description {
+                        typ: ControlType::Integer,
+                        minimum: control.minimum_value().into(),
+                        maximum: control.maximum_value().into(),
+                        step: control.step() as u64,
+                        default: control.default().into(),
+                    }
+                });
+            },
+            BacklightComp => {
+                ctrls.insert(CamControl::BacklightCompensation, DescriptionInt {
+                    kcontrol: *kcontrol,
+                    sec2degree: false,
+                    descr: Description {
+                        typ: ControlType::Integer,
+                        minimum: control.minimum_value().into(),
+                        maximum: control.maximum_value().into(),
+                        step: control.step() as u64,
+                        default: control.default().into(),
+                    }
+                });
+            },
+            Sharpness => {
+                ctrls.insert(CamControl::Sharpness, DescriptionInt {
+                    kcontrol: *kcontrol,
+                    sec2degree: false,
+                    descr: Description {
+                        typ: ControlType::Integer,
+                        minimum: control.minimum_value().into(),
+                        maximum: control.maximum_value().into(),
+                        step: control.step() as u64,
+                        default: control.default().into(),
+                    }
+                });
+            },
+            Saturation => {
+                ctrls.insert(CamControl::Saturation, DescriptionInt {
+                    kcontrol: *kcontrol,
+                    sec2degree: false,
+                    descr: Description {
+                        typ: ControlType::Integer,
+                        minimum: control.minimum_value().into(),
+                        maximum: control.maximum_value().into(),
+                        step: control.step() as u64,
+                        default: control.default().into(),
+                    }
+                });
+            },
+            Contrast => {
+                ctrls.insert(CamControl::Contrast, DescriptionInt {
+                    kcontrol: *kcontrol,
+                    sec2degree: false,
+                    descr: Description {
+                        typ: ControlType::Integer,
+                        minimum: control.minimum_value().into(),
+                        maximum: control.maximum_value().into(),
+                        step: control.step() as u64,
+                        default: control.default().into(),
+                    }
+                });
+            },
+            Gamma => {
+                ctrls.insert(CamControl::Gamma, DescriptionInt {
+                    kcontrol: *kcontrol,
+                    sec2degree: false,
+                    descr: Description {
+                        typ: ControlType::Integer,
+                        minimum: control.minimum_value().into(),
+                        maximum: control.maximum_value().into(),
+                        step: control.step() as u64,
+                        default: control.default().into(),
+                    }
+                });
+            },
+            //Hue,ColorEnable,Roll,Iris,
             _ => ()
         }
     }
@@ -140,10 +256,18 @@ impl CamInterno {
             ControlType::Integer => {
                 if ctrldescr.sec2degree { vl /= 3600; }
                 ctrl.set_value(vl as i32)?;
+                // the exposure time shares the control of ExposureAuto and is only
+                // written in manual mode: keep the flag Manual
+                if camctrl == CamControl::ExposureAbsolute { ctrl.set_active(false); }
             },
             ControlType::Boolean => {
                 ctrl.set_active(if vl != 0 {true} else {false});
-            }
+            },
+            ControlType::Menu => { // auto exposure: 1 manual, 3 automatic (linux values)
+                ctrl.set_active(if vl != 1 {true} else {false});
+            },
+            // nokhwa has no pan/tilt reset, none is registered
+            ControlType::Button => return Err(UVIError::CamControlNotFound)
         }
         self.dev.set_camera_control(ctrl)?;
         Ok(())
@@ -162,7 +286,14 @@ impl CamInterno {
                     nokhwa::KnownCameraControlFlag::Manual => Ok(0),
                     nokhwa::KnownCameraControlFlag::Automatic => Ok(1)
                 }
-            }
+            },
+            ControlType::Menu => {
+                match res.flag() {
+                    nokhwa::KnownCameraControlFlag::Manual => Ok(1),
+                    nokhwa::KnownCameraControlFlag::Automatic => Ok(3)
+                }
+            },
+            ControlType::Button => Err(UVIError::CamControlNotFound)
         }
     } 
 
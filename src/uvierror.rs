@@ -8,6 +8,8 @@ use nokhwa;
 pub enum UVIError {
   BadDirs,
   CamControlNotFound,
+  CommandNotSupported,
+  CommandCancelled,
   #[cfg(all(not(feature="uvcmock"),target_os = "linux"))]
   UnknownCameraControlValue,
   CameraNotFound,
@@ -24,6 +26,8 @@ impl fmt::Display for UVIError {
     match *self {
       UVIError::BadDirs => write!(f, "Couldn't manipulate config directories"),
       UVIError::CamControlNotFound => write!(f, "This camera control is not available for device"),
+      UVIError::CommandNotSupported => write!(f, "This command is not supported for device"),
+      UVIError::CommandCancelled => write!(f, "The command was cancelled before the end"),
       #[cfg(all(not(feature="uvcmock"),target_os = "linux"))]
       UVIError::UnknownCameraControlValue => write!(f, "This camera control uses unknown value type"),
       UVIError::CameraNotFound => write!(f, "Couldn't access camera device"),
@@ -38,6 +42,20 @@ impl fmt::Display for UVIError {
   }
 }
 
+impl UVIError {
+  // the camera device (or the task talking to it) is gone
+  pub fn is_fatal(&self) -> bool {
+    match *self {
+      UVIError::AsyncChannelClosed => true,
+      UVIError::AsyncChannelNoSender => true,
+      UVIError::IoError(_) => true,
+      #[cfg(target_os = "windows")]
+      UVIError::NokhwaError(_) => true,
+      _ => false
+    }
+  }
+}
+
 impl From<rusqlite::Error> for UVIError {
   fn from(err: rusqlite::Error) -> Self {
       UVIError::RusqliteError(err)
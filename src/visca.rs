@@ -0,0 +1,872 @@
+use std::fmt;
+
+/* VISCA message codec: raw packets <-> typed messages
+  A packet is: header, message bytes, 0xFF terminator.
+  The header byte is 1SSS0RRR (sender and receiver addresses), or 0x88 for broadcasts.
+*/
+
+pub fn int_to_nibbles(v : i64, size: usize) -> Vec<u8> {
+    let mut p = v;
+    let mut s: Vec<u8> = Vec::new();
+    for _i in 0..size {
+        s.insert(0, (p & 0xF) as u8);
+        p = p >> 4;
+    }
+    return s;
+}
+
+// signed (two's complement) value
+pub fn nibbles_to_int(nibbles: &[u8]) -> i64 {
+    let r = nibbles_to_uint(nibbles);
+    if r & (0x8<<(4*nibbles.len()-4)) != 0 {
+        return r - (0x1<<(4*nibbles.len()));
+    }
+    return r;
+}
+
+pub fn nibbles_to_uint(nibbles: &[u8]) -> i64 {
+    let mut r: i64 = 0;
+    for i in 0..nibbles.len() {
+        r = (r<<4) + ((nibbles[i] & 0xF) as i64);
+    }
+    return r;
+}
+
+pub const BROADCAST: u8 = 0x88;
+
+pub fn header(sender: u8, receiver: u8) -> u8 {
+    0x80 | ((sender & 0x7)<<4) | (receiver & 0xF)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LensDrive {
+    Stop,
+    Plus(Option<u8>), // Tele / Far, standard or variable speed (0-7)
+    Minus(Option<u8>), // Wide / Near, standard or variable speed (0-7)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FocusMode {
+    Auto,
+    Manual,
+    Toggle,
+}
+
+// settings with Reset/Up/Down (04 0x) and Direct (04 4x) commands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exposure {
+    Shutter,
+    Iris,
+    Gain,
+    Bright,
+    ExpComp,
+}
+
+impl Exposure {
+    fn opcode(&self) -> u8 {
+        match *self {
+            Exposure::Shutter => 0x0A,
+            Exposure::Iris => 0x0B,
+            Exposure::Gain => 0x0C,
+            Exposure::Bright => 0x0D,
+            Exposure::ExpComp => 0x0E,
+        }
+    }
+    fn from_opcode(op: u8) -> Option<Exposure> {
+        match op & 0x0F {
+            0x0A => Some(Exposure::Shutter),
+            0x0B => Some(Exposure::Iris),
+            0x0C => Some(Exposure::Gain),
+            0x0D => Some(Exposure::Bright),
+            0x0E => Some(Exposure::ExpComp),
+            _ => None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Adjust {
+    Reset,
+    Up,
+    Down,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ViscaCommand {
+    IfClear(),
+    PresetReset(u8),
+    PresetSet(u8),
+    PresetRecall(u8),
+    PanTiltHome(),
+    PanTiltReset(),
+    // directions: 1 left/up, 2 right/down, 3 stop
+    PanTiltDrive { pan_speed: u8, tilt_speed: u8, pan_dir: u8, tilt_dir: u8 },
+    PanTiltAbsolute { pan_speed: u8, tilt_speed: u8, pan: i64, tilt: i64 },
+    PanTiltRelative { pan_speed: u8, tilt_speed: u8, pan: i64, tilt: i64 },
+    // corners: 0 DownLeft, 1 UpRight
+    PanTiltLimitSet { corner: u8, pan: i64, tilt: i64 },
+    PanTiltLimitClear(u8),
+    PresetSpeed(u8), // 01-18
+    Tally(bool),
+    Zoom(LensDrive),
+    ZoomDirect(u16),
+    ZoomFocusDirect(u16, u16), // zoom, focus
+    DigitalZoom(bool),
+    FocusMode(FocusMode),
+    Focus(LensDrive),
+    FocusDirect(u16),
+    FocusOnePushTrigger(),
+    FocusInfinity(),
+    AfSensitivity(u8), // 02 Normal, 03 Low
+    AfMode(u8), // 00 Normal, 01 Interval, 02 Zoom Trigger
+    AfIntervalTime(u8, u8), // seconds: AF active, then staying
+    WhiteBalanceOnePushTrigger(),
+    WhiteBalanceMode(u8),
+    RGain(Adjust),
+    RGainDirect(u8),
+    BGain(Adjust),
+    BGainDirect(u8),
+    AeMode(u8), // 00 Full Auto, 03 Manual, 0A Shutter Priority, 0B Iris Priority, 0D Bright
+    ExposureAdjust(Exposure, Adjust),
+    ExposureDirect(Exposure, u8), // position
+    ExpCompMode(bool),
+    Backlight(bool),
+    LrReverse(bool),
+    PictureFlip(bool),
+    Flip(u8), // 0 Off, 1 Flip-H, 2 Flip-V, 3 Flip-HV
+    ApertureAdjust(Adjust),
+    ApertureDirect(u8), // 0-F
+    ColorGain(u8), // 0 (60%) - E (200%)
+    Contrast(u8), // 00-FF
+    Gamma(u8), // 0-4
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ViscaInquiry {
+    Version(),
+    Preset(),
+    PanTiltMaxSpeed(),
+    PanTiltPos(),
+    Tally(),
+    FocusMode(),
+    AfSensitivity(),
+    AfMode(),
+    AfIntervalTime(),
+    WhiteBalanceMode(),
+    RGain(),
+    BGain(),
+    ZoomPos(),
+    DigitalZoom(),
+    FocusPos(),
+    PanTiltLimit(u8), // not in the Sony documents: 8x 09 06 07 0W FF
+    AeMode(),
+    ExposurePos(Exposure),
+    ExpCompMode(),
+    Backlight(),
+    LrReverse(),
+    PictureFlip(),
+    Flip(),
+    Aperture(),
+    ColorGain(),
+    Contrast(),
+    Gamma(),
+    Block(u8),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ViscaMessage {
+    Command(ViscaCommand),
+    Inquiry(ViscaInquiry),
+    Cancel(u8), // socket
+    AddressSet(u8), // broadcast: address of the first camera of the chain
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViscaErrorCode {
+    MessageLength,
+    Syntax,
+    BufferFull,
+    Cancelled,
+    NoSocket,
+    NotExecutable,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ViscaReply {
+    Ack(u8), // socket
+    Completion(u8), // socket
+    InquiryData(Vec<u8>), // bytes following 50, never empty
+    Error(u8, ViscaErrorCode), // socket
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ViscaDecodeError {
+    NotTerminated,
+    Truncated,
+    BadParameter,
+    Unknown,
+}
+
+impl fmt::Display for ViscaDecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ViscaDecodeError::NotTerminated => write!(f, "VISCA packet without header or terminator"),
+            ViscaDecodeError::Truncated => write!(f, "VISCA packet with wrong length for its opcode"),
+            ViscaDecodeError::BadParameter => write!(f, "VISCA packet with a parameter out of range"),
+            ViscaDecodeError::Unknown => write!(f, "VISCA packet with unknown opcode"),
+        }
+    }
+}
+
+impl ViscaErrorCode {
+    fn code(&self) -> u8 {
+        match *self {
+            ViscaErrorCode::MessageLength => 0x01,
+            ViscaErrorCode::Syntax => 0x02,
+            ViscaErrorCode::BufferFull => 0x03,
+            ViscaErrorCode::Cancelled => 0x04,
+            ViscaErrorCode::NoSocket => 0x05,
+            ViscaErrorCode::NotExecutable => 0x41,
+        }
+    }
+    fn from_code(code: u8) -> Option<ViscaErrorCode> {
+        match code {
+            0x01 => Some(ViscaErrorCode::MessageLength),
+            0x02 => Some(ViscaErrorCode::Syntax),
+            0x03 => Some(ViscaErrorCode::BufferFull),
+            0x04 => Some(ViscaErrorCode::Cancelled),
+            0x05 => Some(ViscaErrorCode::NoSocket),
+            0x41 => Some(ViscaErrorCode::NotExecutable),
+            _ => None
+        }
+    }
+}
+
+// parameters of an opcode: exactly n bytes
+fn params(p: &[u8], n: usize) -> Result<&[u8], ViscaDecodeError> {
+    if p.len() != n { return Err(ViscaDecodeError::Truncated); }
+    Ok(p)
+}
+
+// parameters of an opcode: exactly n bytes, all of them 0x0N
+fn nibble_params(p: &[u8], n: usize) -> Result<&[u8], ViscaDecodeError> {
+    let p = params(p, n)?;
+    if p.iter().any(|b| b & 0xF0 != 0) { return Err(ViscaDecodeError::BadParameter); }
+    Ok(p)
+}
+
+fn decode_lens_drive(b: u8) -> Result<LensDrive, ViscaDecodeError> {
+    match b {
+        0x00 => Ok(LensDrive::Stop),
+        0x02 => Ok(LensDrive::Plus(None)),
+        0x03 => Ok(LensDrive::Minus(None)),
+        0x20..=0x27 => Ok(LensDrive::Plus(Some(b & 0x7))),
+        0x30..=0x37 => Ok(LensDrive::Minus(Some(b & 0x7))),
+        _ => Err(ViscaDecodeError::BadParameter)
+    }
+}
+
+fn encode_lens_drive(d: &LensDrive) -> u8 {
+    match *d {
+        LensDrive::Stop => 0x00,
+        LensDrive::Plus(None) => 0x02,
+        LensDrive::Minus(None) => 0x03,
+        LensDrive::Plus(Some(p)) => 0x20 | (p & 0x7),
+        LensDrive::Minus(Some(p)) => 0x30 | (p & 0x7),
+    }
+}
+
+fn decode_adjust(b: u8) -> Result<Adjust, ViscaDecodeError> {
+    match b {
+        0x00 => Ok(Adjust::Reset),
+        0x02 => Ok(Adjust::Up),
+        0x03 => Ok(Adjust::Down),
+        _ => Err(ViscaDecodeError::BadParameter)
+    }
+}
+
+fn encode_adjust(a: &Adjust) -> u8 {
+    match *a {
+        Adjust::Reset => 0x00,
+        Adjust::Up => 0x02,
+        Adjust::Down => 0x03,
+    }
+}
+
+fn decode_on_off(b: u8) -> Result<bool, ViscaDecodeError> {
+    match b {
+        0x02 => Ok(true),
+        0x03 => Ok(false),
+        _ => Err(ViscaDecodeError::BadParameter)
+    }
+}
+
+fn encode_on_off(on: bool) -> u8 {
+    if on { 0x02 } else { 0x03 }
+}
+
+fn decode_pan_tilt_pos(p: &[u8]) -> Result<(u8, u8, i64, i64), ViscaDecodeError> {
+    let p = params(p, 10)?;
+    nibble_params(&p[2..], 8)?;
+    Ok((p[0], p[1], nibbles_to_int(&p[2..6]), nibbles_to_int(&p[6..10])))
+}
+
+fn decode_command(m: &[u8]) -> Result<ViscaCommand, ViscaDecodeError> {
+    if m.len() < 2 { return Err(ViscaDecodeError::Truncated); }
+    let p = &m[2..];
+    match (m[0], m[1]) {
+        (0x00, 0x01) => { params(p, 0)?; Ok(ViscaCommand::IfClear()) },
+        (0x04, 0x3F) => { // Cam Memory
+            let p = params(p, 2)?;
+            match p[0] {
+                0x00 => Ok(ViscaCommand::PresetReset(p[1])),
+                0x01 => Ok(ViscaCommand::PresetSet(p[1])),
+                0x02 => Ok(ViscaCommand::PresetRecall(p[1])),
+                _ => Err(ViscaDecodeError::BadParameter)
+            }
+        },
+        (0x06, 0x04) => { params(p, 0)?; Ok(ViscaCommand::PanTiltHome()) },
+        (0x06, 0x05) => { params(p, 0)?; Ok(ViscaCommand::PanTiltReset()) },
+        (0x7E, 0x01) => { // PTZOptics/Sony extensions
+            if p.len() < 1 { return Err(ViscaDecodeError::Truncated); }
+            match p[0] {
+                0x0B => {
+                    let p = params(&p[1..], 1)?;
+                    if p[0] < 1 || p[0] > 0x18 { return Err(ViscaDecodeError::BadParameter); }
+                    Ok(ViscaCommand::PresetSpeed(p[0]))
+                },
+                0x0A => {
+                    let p = params(&p[1..], 2)?;
+                    if p[0] != 0x00 { return Err(ViscaDecodeError::BadParameter); }
+                    Ok(ViscaCommand::Tally(decode_on_off(p[1])?))
+                },
+                _ => Err(ViscaDecodeError::Unknown)
+            }
+        },
+        (0x06, 0x01) => {
+            let p = params(p, 4)?;
+            if p[2] < 1 || p[2] > 3 || p[3] < 1 || p[3] > 3 { return Err(ViscaDecodeError::BadParameter); }
+            Ok(ViscaCommand::PanTiltDrive { pan_speed: p[0], tilt_speed: p[1], pan_dir: p[2], tilt_dir: p[3] })
+        },
+        (0x06, 0x02) => {
+            let (pan_speed, tilt_speed, pan, tilt) = decode_pan_tilt_pos(p)?;
+            Ok(ViscaCommand::PanTiltAbsolute { pan_speed, tilt_speed, pan, tilt })
+        },
+        (0x06, 0x03) => {
+            let (pan_speed, tilt_speed, pan, tilt) = decode_pan_tilt_pos(p)?;
+            Ok(ViscaCommand::PanTiltRelative { pan_speed, tilt_speed, pan, tilt })
+        },
+        (0x06, 0x07) => {
+            let p = params(p, 10)?;
+            nibble_params(&p[2..], 8)?;
+            if p[1] > 1 { return Err(ViscaDecodeError::BadParameter); }
+            match p[0] {
+                0x00 => Ok(ViscaCommand::PanTiltLimitSet {
+                    corner: p[1], pan: nibbles_to_int(&p[2..6]), tilt: nibbles_to_int(&p[6..10])
+                }),
+                0x01 => Ok(ViscaCommand::PanTiltLimitClear(p[1])), // 07 0F 0F 0F 07 0F 0F 0F
+                _ => Err(ViscaDecodeError::BadParameter)
+            }
+        },
+        (0x04, 0x07) => Ok(ViscaCommand::Zoom(decode_lens_drive(params(p, 1)?[0])?)),
+        (0x04, 0x47) => {
+            // zoom alone or zoom and focus together
+            let p = nibble_params(p, if p.len() == 8 { 8 } else { 4 })?;
+            let zoom = nibbles_to_uint(&p[0..4]) as u16;
+            match p.len() {
+                8 => Ok(ViscaCommand::ZoomFocusDirect(zoom, nibbles_to_uint(&p[4..8]) as u16)),
+                _ => Ok(ViscaCommand::ZoomDirect(zoom))
+            }
+        },
+        (0x04, 0x06) => Ok(ViscaCommand::DigitalZoom(decode_on_off(params(p, 1)?[0])?)),
+        (0x04, 0x38) => {
+            match params(p, 1)?[0] {
+                0x02 => Ok(ViscaCommand::FocusMode(FocusMode::Auto)),
+                0x03 => Ok(ViscaCommand::FocusMode(FocusMode::Manual)),
+                0x10 => Ok(ViscaCommand::FocusMode(FocusMode::Toggle)),
+                _ => Err(ViscaDecodeError::BadParameter)
+            }
+        },
+        (0x04, 0x08) => Ok(ViscaCommand::Focus(decode_lens_drive(params(p, 1)?[0])?)),
+        (0x04, 0x48) => Ok(ViscaCommand::FocusDirect(nibbles_to_uint(nibble_params(p, 4)?) as u16)),
+        (0x04, 0x18) => {
+            match params(p, 1)?[0] {
+                0x01 => Ok(ViscaCommand::FocusOnePushTrigger()),
+                0x02 => Ok(ViscaCommand::FocusInfinity()),
+                _ => Err(ViscaDecodeError::BadParameter)
+            }
+        },
+        (0x04, 0x58) => {
+            let p = params(p, 1)?;
+            if p[0] < 2 || p[0] > 3 { return Err(ViscaDecodeError::BadParameter); }
+            Ok(ViscaCommand::AfSensitivity(p[0]))
+        },
+        (0x04, 0x57) => {
+            let p = params(p, 1)?;
+            if p[0] > 2 { return Err(ViscaDecodeError::BadParameter); }
+            Ok(ViscaCommand::AfMode(p[0]))
+        },
+        (0x04, 0x27) => {
+            let p = nibble_params(p, 4)?;
+            Ok(ViscaCommand::AfIntervalTime(nibbles_to_uint(&p[0..2]) as u8, nibbles_to_uint(&p[2..4]) as u8))
+        },
+        (0x04, 0x10) => {
+            match params(p, 1)?[0] {
+                0x05 => Ok(ViscaCommand::WhiteBalanceOnePushTrigger()),
+                _ => Err(ViscaDecodeError::BadParameter)
+            }
+        },
+        (0x04, 0x35) => {
+            let p = params(p, 1)?;
+            if p[0] > 0x0F { return Err(ViscaDecodeError::BadParameter); }
+            Ok(ViscaCommand::WhiteBalanceMode(p[0]))
+        },
+        (0x04, 0x03) => Ok(ViscaCommand::RGain(decode_adjust(params(p, 1)?[0])?)),
+        (0x04, 0x04) => Ok(ViscaCommand::BGain(decode_adjust(params(p, 1)?[0])?)),
+        (0x04, 0x43) => Ok(ViscaCommand::RGainDirect(nibbles_to_uint(nibble_params(p, 4)?) as u8)),
+        (0x04, 0x44) => Ok(ViscaCommand::BGainDirect(nibbles_to_uint(nibble_params(p, 4)?) as u8)),
+        (0x04, 0x39) => {
+            let p = params(p, 1)?;
+            match p[0] {
+                0x00 | 0x03 | 0x0A | 0x0B | 0x0D => Ok(ViscaCommand::AeMode(p[0])),
+                _ => Err(ViscaDecodeError::BadParameter)
+            }
+        },
+        (0x04, op @ 0x0A..=0x0E) => {
+            let e = Exposure::from_opcode(op).ok_or(ViscaDecodeError::Unknown)?;
+            Ok(ViscaCommand::ExposureAdjust(e, decode_adjust(params(p, 1)?[0])?))
+        },
+        (0x04, op @ 0x4A..=0x4E) => {
+            let e = Exposure::from_opcode(op).ok_or(ViscaDecodeError::Unknown)?;
+            Ok(ViscaCommand::ExposureDirect(e, nibbles_to_uint(nibble_params(p, 4)?) as u8))
+        },
+        (0x04, 0x3E) => Ok(ViscaCommand::ExpCompMode(decode_on_off(params(p, 1)?[0])?)),
+        (0x04, 0x33) => Ok(ViscaCommand::Backlight(decode_on_off(params(p, 1)?[0])?)),
+        (0x04, 0x61) => Ok(ViscaCommand::LrReverse(decode_on_off(params(p, 1)?[0])?)),
+        (0x04, 0x66) => Ok(ViscaCommand::PictureFlip(decode_on_off(params(p, 1)?[0])?)),
+        (0x04, 0xA4) => {
+            let p = params(p, 1)?;
+            if p[0] > 3 { return Err(ViscaDecodeError::BadParameter); }
+            Ok(ViscaCommand::Flip(p[0]))
+        },
+        (0x04, 0x02) => Ok(ViscaCommand::ApertureAdjust(decode_adjust(params(p, 1)?[0])?)),
+        (0x04, 0x42) => {
+            let a = nibbles_to_uint(nibble_params(p, 4)?);
+            if a > 0x0F { return Err(ViscaDecodeError::BadParameter); }
+            Ok(ViscaCommand::ApertureDirect(a as u8))
+        },
+        (0x04, 0x49) => {
+            let g = nibbles_to_uint(nibble_params(p, 4)?);
+            if g > 0x0E { return Err(ViscaDecodeError::BadParameter); }
+            Ok(ViscaCommand::ColorGain(g as u8))
+        },
+        (0x04, 0xA2) => Ok(ViscaCommand::Contrast(nibbles_to_uint(nibble_params(p, 4)?) as u8)),
+        (0x04, 0x5B) => {
+            let p = params(p, 1)?;
+            if p[0] > 4 { return Err(ViscaDecodeError::BadParameter); }
+            Ok(ViscaCommand::Gamma(p[0]))
+        },
+        _ => Err(ViscaDecodeError::Unknown)
+    }
+}
+
+fn decode_inquiry(m: &[u8]) -> Result<ViscaInquiry, ViscaDecodeError> {
+    if m.len() < 2 { return Err(ViscaDecodeError::Truncated); }
+    let p = &m[2..];
+    match (m[0], m[1]) {
+        (0x00, 0x02) => { params(p, 0)?; Ok(ViscaInquiry::Version()) },
+        (0x04, 0x3F) => { params(p, 0)?; Ok(ViscaInquiry::Preset()) },
+        (0x06, 0x11) => { params(p, 0)?; Ok(ViscaInquiry::PanTiltMaxSpeed()) },
+        (0x06, 0x12) => { params(p, 0)?; Ok(ViscaInquiry::PanTiltPos()) },
+        (0x04, 0x38) => { params(p, 0)?; Ok(ViscaInquiry::FocusMode()) },
+        (0x04, 0x58) => { params(p, 0)?; Ok(ViscaInquiry::AfSensitivity()) },
+        (0x04, 0x57) => { params(p, 0)?; Ok(ViscaInquiry::AfMode()) },
+        (0x04, 0x27) => { params(p, 0)?; Ok(ViscaInquiry::AfIntervalTime()) },
+        (0x04, 0x35) => { params(p, 0)?; Ok(ViscaInquiry::WhiteBalanceMode()) },
+        (0x04, 0x43) => { params(p, 0)?; Ok(ViscaInquiry::RGain()) },
+        (0x04, 0x44) => { params(p, 0)?; Ok(ViscaInquiry::BGain()) },
+        (0x04, 0x47) => { params(p, 0)?; Ok(ViscaInquiry::ZoomPos()) },
+        (0x04, 0x06) => { params(p, 0)?; Ok(ViscaInquiry::DigitalZoom()) },
+        (0x06, 0x07) => {
+            let p = params(p, 1)?;
+            if p[0] > 1 { return Err(ViscaDecodeError::BadParameter); }
+            Ok(ViscaInquiry::PanTiltLimit(p[0]))
+        },
+        (0x04, 0x48) => { params(p, 0)?; Ok(ViscaInquiry::FocusPos()) },
+        (0x04, 0x39) => { params(p, 0)?; Ok(ViscaInquiry::AeMode()) },
+        (0x04, op @ 0x4A..=0x4E) => {
+            params(p, 0)?;
+            Ok(ViscaInquiry::ExposurePos(Exposure::from_opcode(op).ok_or(ViscaDecodeError::Unknown)?))
+        },
+        (0x04, 0x3E) => { params(p, 0)?; Ok(ViscaInquiry::ExpCompMode()) },
+        (0x04, 0x33) => { params(p, 0)?; Ok(ViscaInquiry::Backlight()) },
+        (0x04, 0x61) => { params(p, 0)?; Ok(ViscaInquiry::LrReverse()) },
+        (0x04, 0x66) => { params(p, 0)?; Ok(ViscaInquiry::PictureFlip()) },
+        (0x04, 0xA4) => { params(p, 0)?; Ok(ViscaInquiry::Flip()) },
+        (0x04, 0x42) => { params(p, 0)?; Ok(ViscaInquiry::Aperture()) },
+        (0x04, 0x49) => { params(p, 0)?; Ok(ViscaInquiry::ColorGain()) },
+        (0x04, 0xA2) => { params(p, 0)?; Ok(ViscaInquiry::Contrast()) },
+        (0x04, 0x5B) => { params(p, 0)?; Ok(ViscaInquiry::Gamma()) },
+        (0x7E, 0x7E) => Ok(ViscaInquiry::Block(params(p, 1)?[0])),
+        (0x7E, 0x01) => {
+            match params(p, 1)?[0] {
+                0x0A => Ok(ViscaInquiry::Tally()),
+                _ => Err(ViscaDecodeError::Unknown)
+            }
+        },
+        _ => Err(ViscaDecodeError::Unknown)
+    }
+}
+
+// pkt: header .. FF, returns the header byte and the message
+pub fn decode(pkt: &[u8]) -> Result<(u8, ViscaMessage), ViscaDecodeError> {
+    if pkt.len() < 2 || pkt[0] & 0x80 == 0 || pkt[pkt.len()-1] != 0xFF {
+        return Err(ViscaDecodeError::NotTerminated);
+    }
+    let m = &pkt[1..pkt.len()-1];
+    if m.len() < 1 { return Err(ViscaDecodeError::Truncated); }
+    match m[0] {
+        0x01 => Ok((pkt[0], ViscaMessage::Command(decode_command(&m[1..])?))),
+        0x09 => Ok((pkt[0], ViscaMessage::Inquiry(decode_inquiry(&m[1..])?))),
+        0x20..=0x2F => { params(&m[1..], 0)?; Ok((pkt[0], ViscaMessage::Cancel(m[0] & 0xF))) },
+        0x30 => {
+            let p = nibble_params(&m[1..], 1)?;
+            if p[0] < 1 || p[0] > 7 { return Err(ViscaDecodeError::BadParameter); }
+            Ok((pkt[0], ViscaMessage::AddressSet(p[0])))
+        },
+        _ => Err(ViscaDecodeError::Unknown)
+    }
+}
+
+fn encode_pan_tilt_pos(v: &mut Vec<u8>, pan_speed: u8, tilt_speed: u8, pan: i64, tilt: i64) {
+    v.extend([pan_speed, tilt_speed]);
+    v.extend(int_to_nibbles(pan, 4));
+    v.extend(int_to_nibbles(tilt, 4));
+}
+
+// controller side of the codec
+pub fn encode(hdr: u8, msg: &ViscaMessage) -> Vec<u8> {
+    let mut v = vec![hdr];
+    match msg {
+        ViscaMessage::Command(cmd) => {
+            v.push(0x01);
+            match cmd {
+                ViscaCommand::IfClear() => v.extend([0x00, 0x01]),
+                ViscaCommand::PresetReset(n) => v.extend([0x04, 0x3F, 0x00, *n]),
+                ViscaCommand::PresetSet(n) => v.extend([0x04, 0x3F, 0x01, *n]),
+                ViscaCommand::PresetRecall(n) => v.extend([0x04, 0x3F, 0x02, *n]),
+                ViscaCommand::PanTiltHome() => v.extend([0x06, 0x04]),
+                ViscaCommand::PanTiltReset() => v.extend([0x06, 0x05]),
+                ViscaCommand::PresetSpeed(speed) => v.extend([0x7E, 0x01, 0x0B, *speed]),
+                ViscaCommand::Tally(on) => v.extend([0x7E, 0x01, 0x0A, 0x00, encode_on_off(*on)]),
+                ViscaCommand::PanTiltDrive { pan_speed, tilt_speed, pan_dir, tilt_dir } =>
+                    v.extend([0x06, 0x01, *pan_speed, *tilt_speed, *pan_dir, *tilt_dir]),
+                ViscaCommand::PanTiltAbsolute { pan_speed, tilt_speed, pan, tilt } => {
+                    v.extend([0x06, 0x02]);
+                    encode_pan_tilt_pos(&mut v, *pan_speed, *tilt_speed, *pan, *tilt);
+                },
+                ViscaCommand::PanTiltRelative { pan_speed, tilt_speed, pan, tilt } => {
+                    v.extend([0x06, 0x03]);
+                    encode_pan_tilt_pos(&mut v, *pan_speed, *tilt_speed, *pan, *tilt);
+                },
+                ViscaCommand::PanTiltLimitSet { corner, pan, tilt } => {
+                    v.extend([0x06, 0x07, 0x00, *corner]);
+                    v.extend(int_to_nibbles(*pan, 4));
+                    v.extend(int_to_nibbles(*tilt, 4));
+                },
+                ViscaCommand::PanTiltLimitClear(corner) =>
+                    v.extend([0x06, 0x07, 0x01, *corner, 0x07, 0x0F, 0x0F, 0x0F, 0x07, 0x0F, 0x0F, 0x0F]),
+                ViscaCommand::Zoom(d) => v.extend([0x04, 0x07, encode_lens_drive(d)]),
+                ViscaCommand::ZoomDirect(z) => {
+                    v.extend([0x04, 0x47]);
+                    v.extend(int_to_nibbles(*z as i64, 4));
+                },
+                ViscaCommand::ZoomFocusDirect(z, f) => {
+                    v.extend([0x04, 0x47]);
+                    v.extend(int_to_nibbles(*z as i64, 4));
+                    v.extend(int_to_nibbles(*f as i64, 4));
+                },
+                ViscaCommand::DigitalZoom(on) => v.extend([0x04, 0x06, encode_on_off(*on)]),
+                ViscaCommand::FocusMode(FocusMode::Auto) => v.extend([0x04, 0x38, 0x02]),
+                ViscaCommand::FocusMode(FocusMode::Manual) => v.extend([0x04, 0x38, 0x03]),
+                ViscaCommand::FocusMode(FocusMode::Toggle) => v.extend([0x04, 0x38, 0x10]),
+                ViscaCommand::Focus(d) => v.extend([0x04, 0x08, encode_lens_drive(d)]),
+                ViscaCommand::FocusDirect(f) => {
+                    v.extend([0x04, 0x48]);
+                    v.extend(int_to_nibbles(*f as i64, 4));
+                },
+                ViscaCommand::FocusOnePushTrigger() => v.extend([0x04, 0x18, 0x01]),
+                ViscaCommand::FocusInfinity() => v.extend([0x04, 0x18, 0x02]),
+                ViscaCommand::AfSensitivity(s) => v.extend([0x04, 0x58, *s]),
+                ViscaCommand::AfMode(m) => v.extend([0x04, 0x57, *m]),
+                ViscaCommand::AfIntervalTime(active, staying) => {
+                    v.extend([0x04, 0x27]);
+                    v.extend(int_to_nibbles(*active as i64, 2));
+                    v.extend(int_to_nibbles(*staying as i64, 2));
+                },
+                ViscaCommand::WhiteBalanceOnePushTrigger() => v.extend([0x04, 0x10, 0x05]),
+                ViscaCommand::WhiteBalanceMode(m) => v.extend([0x04, 0x35, *m]),
+                ViscaCommand::RGain(a) => v.extend([0x04, 0x03, encode_adjust(a)]),
+                ViscaCommand::BGain(a) => v.extend([0x04, 0x04, encode_adjust(a)]),
+                ViscaCommand::RGainDirect(g) => {
+                    v.extend([0x04, 0x43]);
+                    v.extend(int_to_nibbles(*g as i64, 4));
+                },
+                ViscaCommand::BGainDirect(g) => {
+                    v.extend([0x04, 0x44]);
+                    v.extend(int_to_nibbles(*g as i64, 4));
+                },
+                ViscaCommand::AeMode(m) => v.extend([0x04, 0x39, *m]),
+                ViscaCommand::ExposureAdjust(e, a) => v.extend([0x04, e.opcode(), encode_adjust(a)]),
+                ViscaCommand::ExposureDirect(e, pos) => {
+                    v.extend([0x04, 0x40 | e.opcode()]);
+                    v.extend(int_to_nibbles(*pos as i64, 4));
+                },
+                ViscaCommand::ExpCompMode(on) => v.extend([0x04, 0x3E, encode_on_off(*on)]),
+                ViscaCommand::Backlight(on) => v.extend([0x04, 0x33, encode_on_off(*on)]),
+                ViscaCommand::LrReverse(on) => v.extend([0x04, 0x61, encode_on_off(*on)]),
+                ViscaCommand::PictureFlip(on) => v.extend([0x04, 0x66, encode_on_off(*on)]),
+                ViscaCommand::Flip(f) => v.extend([0x04, 0xA4, *f]),
+                ViscaCommand::ApertureAdjust(a) => v.extend([0x04, 0x02, encode_adjust(a)]),
+                ViscaCommand::ApertureDirect(a) => {
+                    v.extend([0x04, 0x42]);
+                    v.extend(int_to_nibbles(*a as i64, 4));
+                },
+                ViscaCommand::ColorGain(g) => {
+                    v.extend([0x04, 0x49]);
+                    v.extend(int_to_nibbles(*g as i64, 4));
+                },
+                ViscaCommand::Contrast(c) => {
+                    v.extend([0x04, 0xA2]);
+                    v.extend(int_to_nibbles(*c as i64, 4));
+                },
+                ViscaCommand::Gamma(g) => v.extend([0x04, 0x5B, *g]),
+            }
+        },
+        ViscaMessage::Inquiry(inq) => {
+            v.push(0x09);
+            match inq {
+                ViscaInquiry::Version() => v.extend([0x00, 0x02]),
+                ViscaInquiry::Preset() => v.extend([0x04, 0x3F]),
+                ViscaInquiry::PanTiltMaxSpeed() => v.extend([0x06, 0x11]),
+                ViscaInquiry::PanTiltPos() => v.extend([0x06, 0x12]),
+                ViscaInquiry::Tally() => v.extend([0x7E, 0x01, 0x0A]),
+                ViscaInquiry::FocusMode() => v.extend([0x04, 0x38]),
+                ViscaInquiry::AfSensitivity() => v.extend([0x04, 0x58]),
+                ViscaInquiry::AfMode() => v.extend([0x04, 0x57]),
+                ViscaInquiry::AfIntervalTime() => v.extend([0x04, 0x27]),
+                ViscaInquiry::WhiteBalanceMode() => v.extend([0x04, 0x35]),
+                ViscaInquiry::RGain() => v.extend([0x04, 0x43]),
+                ViscaInquiry::BGain() => v.extend([0x04, 0x44]),
+                ViscaInquiry::ZoomPos() => v.extend([0x04, 0x47]),
+                ViscaInquiry::DigitalZoom() => v.extend([0x04, 0x06]),
+                ViscaInquiry::FocusPos() => v.extend([0x04, 0x48]),
+                ViscaInquiry::PanTiltLimit(corner) => v.extend([0x06, 0x07, *corner]),
+                ViscaInquiry::AeMode() => v.extend([0x04, 0x39]),
+                ViscaInquiry::ExposurePos(e) => v.extend([0x04, 0x40 | e.opcode()]),
+                ViscaInquiry::ExpCompMode() => v.extend([0x04, 0x3E]),
+                ViscaInquiry::Backlight() => v.extend([0x04, 0x33]),
+                ViscaInquiry::LrReverse() => v.extend([0x04, 0x61]),
+                ViscaInquiry::PictureFlip() => v.extend([0x04, 0x66]),
+                ViscaInquiry::Flip() => v.extend([0x04, 0xA4]),
+                ViscaInquiry::Aperture() => v.extend([0x04, 0x42]),
+                ViscaInquiry::ColorGain() => v.extend([0x04, 0x49]),
+                ViscaInquiry::Contrast() => v.extend([0x04, 0xA2]),
+                ViscaInquiry::Gamma() => v.extend([0x04, 0x5B]),
+                ViscaInquiry::Block(n) => v.extend([0x7E, 0x7E, *n]),
+            }
+        },
+        ViscaMessage::Cancel(socket) => v.push(0x20 | (socket & 0xF)),
+        ViscaMessage::AddressSet(address) => v.extend([0x30, *address & 0xF]),
+    }
+    v.push(0xFF);
+    v
+}
+
+pub fn encode_reply(hdr: u8, reply: &ViscaReply) -> Vec<u8> {
+    let mut v = vec![hdr];
+    match reply {
+        ViscaReply::Ack(socket) => v.push(0x40 | (socket & 0xF)),
+        ViscaReply::Completion(socket) => v.push(0x50 | (socket & 0xF)),
+        ViscaReply::InquiryData(data) => {
+            v.push(0x50);
+            v.extend(data);
+        },
+        ViscaReply::Error(socket, code) => v.extend([0x60 | (socket & 0xF), code.code()]),
+    }
+    v.push(0xFF);
+    v
+}
+
+// controller side of the codec
+pub fn decode_reply(pkt: &[u8]) -> Result<(u8, ViscaReply), ViscaDecodeError> {
+    if pkt.len() < 3 || pkt[0] & 0x80 == 0 || pkt[pkt.len()-1] != 0xFF {
+        return Err(ViscaDecodeError::NotTerminated);
+    }
+    let m = &pkt[1..pkt.len()-1];
+    let socket = m[0] & 0xF;
+    match m[0] & 0xF0 {
+        0x40 => { params(&m[1..], 0)?; Ok((pkt[0], ViscaReply::Ack(socket))) },
+        0x50 if m.len() == 1 => Ok((pkt[0], ViscaReply::Completion(socket))),
+        0x50 if socket == 0 => Ok((pkt[0], ViscaReply::InquiryData(m[1..].to_vec()))),
+        0x60 => {
+            let code = ViscaErrorCode::from_code(params(&m[1..], 1)?[0]).ok_or(ViscaDecodeError::BadParameter)?;
+            Ok((pkt[0], ViscaReply::Error(socket, code)))
+        },
+        _ => Err(ViscaDecodeError::Unknown)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commands() -> Vec<ViscaCommand> {
+        vec![
+            ViscaCommand::IfClear(),
+            ViscaCommand::PresetReset(0x05),
+            ViscaCommand::PresetSet(0x7F),
+            ViscaCommand::PresetRecall(0x00),
+            ViscaCommand::PanTiltHome(),
+            ViscaCommand::PanTiltReset(),
+            ViscaCommand::PanTiltDrive { pan_speed: 0x18, tilt_speed: 0x14, pan_dir: 1, tilt_dir: 3 },
+            ViscaCommand::PanTiltAbsolute { pan_speed: 0x01, tilt_speed: 0x02, pan: -0x1234, tilt: 0x0567 },
+            ViscaCommand::PanTiltRelative { pan_speed: 0x18, tilt_speed: 0x14, pan: 0x7FFF, tilt: -0x8000 },
+            ViscaCommand::PanTiltLimitSet { corner: 1, pan: 0x0800, tilt: -0x0100 },
+            ViscaCommand::PanTiltLimitClear(0),
+            ViscaCommand::PresetSpeed(0x18),
+            ViscaCommand::Tally(true),
+            ViscaCommand::Tally(false),
+            ViscaCommand::Zoom(LensDrive::Stop),
+            ViscaCommand::Zoom(LensDrive::Plus(None)),
+            ViscaCommand::Zoom(LensDrive::Minus(Some(7))),
+            ViscaCommand::ZoomDirect(0x4000),
+            ViscaCommand::ZoomFocusDirect(0x1234, 0xF000),
+            ViscaCommand::DigitalZoom(true),
+            ViscaCommand::FocusMode(FocusMode::Auto),
+            ViscaCommand::FocusMode(FocusMode::Manual),
+            ViscaCommand::FocusMode(FocusMode::Toggle),
+            ViscaCommand::Focus(LensDrive::Minus(None)),
+            ViscaCommand::Focus(LensDrive::Plus(Some(0))),
+            ViscaCommand::FocusDirect(0xABCD),
+            ViscaCommand::FocusOnePushTrigger(),
+            ViscaCommand::FocusInfinity(),
+            ViscaCommand::AfSensitivity(0x03),
+            ViscaCommand::AfMode(0x02),
+            ViscaCommand::AfIntervalTime(5, 0x10),
+            ViscaCommand::WhiteBalanceOnePushTrigger(),
+            ViscaCommand::WhiteBalanceMode(0x05),
+            ViscaCommand::RGain(Adjust::Reset),
+            ViscaCommand::RGainDirect(0xFF),
+            ViscaCommand::BGain(Adjust::Down),
+            ViscaCommand::BGainDirect(0x40),
+            ViscaCommand::AeMode(0x0D),
+            ViscaCommand::ExposureAdjust(Exposure::Shutter, Adjust::Up),
+            ViscaCommand::ExposureAdjust(Exposure::ExpComp, Adjust::Reset),
+            ViscaCommand::ExposureDirect(Exposure::Iris, 0x11),
+            ViscaCommand::ExposureDirect(Exposure::Gain, 0x05),
+            ViscaCommand::ExposureDirect(Exposure::Bright, 0x1F),
+            ViscaCommand::ExpCompMode(false),
+            ViscaCommand::Backlight(true),
+            ViscaCommand::LrReverse(true),
+            ViscaCommand::PictureFlip(false),
+            ViscaCommand::Flip(3),
+            ViscaCommand::ApertureAdjust(Adjust::Up),
+            ViscaCommand::ApertureDirect(0x0F),
+            ViscaCommand::ColorGain(0x0E),
+            ViscaCommand::Contrast(0x80),
+            ViscaCommand::Gamma(4),
+        ]
+    }
+
+    fn inquiries() -> Vec<ViscaInquiry> {
+        vec![
+            ViscaInquiry::Version(),
+            ViscaInquiry::Preset(),
+            ViscaInquiry::PanTiltMaxSpeed(),
+            ViscaInquiry::PanTiltPos(),
+            ViscaInquiry::Tally(),
+            ViscaInquiry::FocusMode(),
+            ViscaInquiry::AfSensitivity(),
+            ViscaInquiry::AfMode(),
+            ViscaInquiry::AfIntervalTime(),
+            ViscaInquiry::WhiteBalanceMode(),
+            ViscaInquiry::RGain(),
+            ViscaInquiry::BGain(),
+            ViscaInquiry::ZoomPos(),
+            ViscaInquiry::DigitalZoom(),
+            ViscaInquiry::FocusPos(),
+            ViscaInquiry::PanTiltLimit(1),
+            ViscaInquiry::AeMode(),
+            ViscaInquiry::ExposurePos(Exposure::Shutter),
+            ViscaInquiry::ExposurePos(Exposure::Iris),
+            ViscaInquiry::ExposurePos(Exposure::Gain),
+            ViscaInquiry::ExposurePos(Exposure::Bright),
+            ViscaInquiry::ExposurePos(Exposure::ExpComp),
+            ViscaInquiry::ExpCompMode(),
+            ViscaInquiry::Backlight(),
+            ViscaInquiry::LrReverse(),
+            ViscaInquiry::PictureFlip(),
+            ViscaInquiry::Flip(),
+            ViscaInquiry::Aperture(),
+            ViscaInquiry::ColorGain(),
+            ViscaInquiry::Contrast(),
+            ViscaInquiry::Gamma(),
+            ViscaInquiry::Block(0x02),
+        ]
+    }
+
+    #[test]
+    fn messages_round_trip() {
+        let mut msgs: Vec<ViscaMessage> = commands().into_iter().map(ViscaMessage::Command).collect();
+        msgs.extend(inquiries().into_iter().map(ViscaMessage::Inquiry));
+        msgs.extend([ViscaMessage::Cancel(2), ViscaMessage::AddressSet(7)]);
+        for msg in msgs {
+            let pkt = encode(header(0, 3), &msg);
+            assert_eq!(decode(&pkt), Ok((0x83, msg.clone())), "{:02X?}", pkt);
+        }
+    }
+
+    #[test]
+    fn replies_round_trip() {
+        let mut replies = vec![
+            ViscaReply::Ack(1),
+            ViscaReply::Completion(2),
+            ViscaReply::InquiryData(vec![0x00, 0x01, 0x0F]),
+        ];
+        for code in [ViscaErrorCode::MessageLength, ViscaErrorCode::Syntax, ViscaErrorCode::BufferFull,
+                ViscaErrorCode::Cancelled, ViscaErrorCode::NoSocket, ViscaErrorCode::NotExecutable] {
+            replies.push(ViscaReply::Error(1, code));
+        }
+        for reply in replies {
+            let pkt = encode_reply(header(2, 0), &reply);
+            assert_eq!(decode_reply(&pkt), Ok((0xA0, reply.clone())), "{:02X?}", pkt);
+        }
+    }
+
+    #[test]
+    fn rejected() {
+        for (pkt, err) in [
+            (&[0x81, 0x01, 0x06, 0x04][..], ViscaDecodeError::NotTerminated),
+            (&[0x01, 0x06, 0x04, 0xFF], ViscaDecodeError::NotTerminated),
+            (&[0x81, 0xFF], ViscaDecodeError::Truncated),
+            (&[0x81, 0x01, 0x06, 0xFF], ViscaDecodeError::Truncated),
+            (&[0x81, 0x01, 0x06, 0x04, 0x00, 0xFF], ViscaDecodeError::Truncated),
+            (&[0x81, 0x01, 0x09, 0x09, 0xFF], ViscaDecodeError::Unknown),
+            // nibbles with the high half set
+            (&[0x81, 0x01, 0x04, 0x47, 0x01, 0x10, 0x00, 0x00, 0xFF], ViscaDecodeError::BadParameter),
+            (&[0x81, 0x01, 0x06, 0x02, 0x18, 0x14, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1F, 0xFF],
+                ViscaDecodeError::BadParameter),
+            (&[0x81, 0x01, 0x04, 0x38, 0x04, 0xFF], ViscaDecodeError::BadParameter),
+            (&[0x81, 0x01, 0x06, 0x01, 0x0C, 0x0A, 0x04, 0x01, 0xFF], ViscaDecodeError::BadParameter),
+            // addresses 1 to 7 only
+            (&[0x88, 0x30, 0x08, 0xFF], ViscaDecodeError::BadParameter),
+            (&[0x88, 0x30, 0x00, 0xFF], ViscaDecodeError::BadParameter),
+        ] {
+            assert_eq!(decode(pkt), Err(err), "{:02X?}", pkt);
+        }
+        assert_eq!(decode_reply(&[0x90, 0x41]), Err(ViscaDecodeError::NotTerminated));
+        assert_eq!(decode_reply(&[0x90, 0x60, 0x42, 0xFF]), Err(ViscaDecodeError::BadParameter));
+        assert_eq!(decode_reply(&[0x90, 0x70, 0xFF]), Err(ViscaDecodeError::Unknown));
+    }
+}
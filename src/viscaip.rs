@@ -1,10 +1,17 @@
-use tokio::net::{TcpListener, TcpStream};
+use tokio::net::{TcpListener, TcpStream, UdpSocket};
 use tokio::io::{AsyncWriteExt, AsyncReadExt};
 use tokio::task;
 use tokio::select;
 use tokio::sync::{mpsc, oneshot, broadcast};
+use tokio::time::{Duration, Instant, sleep_until};
+use std::collections::{BTreeMap, HashMap};
+use std::net;
+use std::sync::{Arc, Mutex};
 use crate::uvierror::UVIError;
 use crate::protos;
+use crate::config;
+use crate::visca;
+use crate::visca::{ViscaMessage, ViscaCommand, ViscaInquiry, ViscaReply, ViscaErrorCode};
 
 /* references:
 - https://www.epiphan.com/userguides/LUMiO12x/Content/UserGuides/PTZ/3-operation/VISCAcommands.htm
@@ -12,64 +19,381 @@ use crate::protos;
 - https://laiatech.com/wp-content/uploads/2021/07/NET-Visca-Commands.pdf
 */
 
-fn int_to_nibbles(v : i64, size: usize) -> Vec<u8> {
-    let mut p = v;
-    let mut s: Vec<u8> = Vec::new();
-    for _i in 0..size {
-        s.insert(0, (p & 0xF) as u8);
-        p = p >> 4;
-    }
-    return s;
+// VISCA over IP (Sony): 8 bytes header before the VISCA message
+// 0-1: payload type, 2-3: payload length, 4-7: sequence number
+const VOIP_VISCA_COMMAND: u16 = 0x0100;
+const VOIP_VISCA_INQUIRY: u16 = 0x0110;
+const VOIP_VISCA_REPLY: u16 = 0x0111;
+const VOIP_VISCA_DEVICE_SETTING: u16 = 0x0120;
+const VOIP_CONTROL_COMMAND: u16 = 0x0200;
+const VOIP_CONTROL_REPLY: u16 = 0x0201;
+const UDP_PEER_TIMEOUT: Duration = Duration::from_secs(60);
+
+// seconds(degree/3600) <-> VISCA pan/tilt positions
+pub fn sec_angle_to_visca(secang: i64) -> i64 {
+    return (secang as f64*2359.0/36000.0).round() as i64;
 }
 
 fn sec_angle_to_nibbles(secang: i64, size: usize) -> Vec<u8> {
-    return int_to_nibbles(secang*2359/36000, size);
+    return visca::int_to_nibbles(sec_angle_to_visca(secang), size);
+}
+
+pub fn visca_to_sec_angle(v: i64) -> i64 {
+    return (v as f64*36000.0/2359.0).round() as i64;
+}
+
+// VISCA speeds -> seconds(degree/3600) per second
+fn pan_speed_to_sec(pan_speed: u8) -> i64 {
+    let mut panspeed = ((pan_speed as i64) % (0x18+1)) * 3600; // 0x18 -> ~ 5sec for 180 degrees
+    if pan_speed > 0x08 { panspeed = 2*panspeed; }
+    if pan_speed > 0x12 { panspeed = 2*panspeed; }
+    panspeed
+}
+fn tilt_speed_to_sec(tilt_speed: u8) -> i64 {
+    ((tilt_speed as i64) % (0x14+1)) * 3600 // 0x14 -> ~2sec for 45 degrees
+}
+
+// speeds of the absolute and relative moves, 0 would never arrive
+fn move_speed(pan_speed: u8, tilt_speed: u8) -> protos::PanTilt {
+    protos::PanTilt {
+        pan: pan_speed_to_sec(std::cmp::max(pan_speed, 1)),
+        tilt: tilt_speed_to_sec(std::cmp::max(tilt_speed, 1))
+    }
+}
+
+// speed of 1 to max -> 1 to scale, the slowest speed still moves
+fn scale_speed(speed: u8, max: u8, scale: u32) -> u8 {
+    if speed == 0 { return 0; }
+    std::cmp::max((std::cmp::min(speed, max) as u32)*scale/(max as u32), 1) as u8
+}
+
+// zoom/focus drive -> -1 to 1
+fn lens_drive_speed(d: &visca::LensDrive) -> f64 {
+    match *d {
+        visca::LensDrive::Stop => 0.0,
+        visca::LensDrive::Plus(None) => 1.0,
+        visca::LensDrive::Minus(None) => -1.0,
+        visca::LensDrive::Plus(Some(p)) => ((1+p) as f64)/8.0,
+        visca::LensDrive::Minus(Some(p)) => -((1+p) as f64)/8.0,
+    }
+}
+
+// identity of the emulated camera: some controllers show or hide features by model
+#[derive(Debug, Clone)]
+struct Profile {
+    name: &'static str,
+    version: [u8; 7], // CAM_VersionInq: vendor, model, ROM version, sockets
+    max_speed: (u8, u8), // pan, tilt, Pan-tiltMaxSpeedInq
+    presets: u16,
+    zoom_max: i64, // Zoom Direct position at the tele end
+    digital_zoom_max: i64, // same, with the digital zoom
+}
+
+const PROFILES: [Profile; 5] = [
+    Profile { name: "generic", version: [0x09,0x99, 0x00,0x01, 0x00,0x01, 0x02],
+        max_speed: (0x18, 0x14), presets: 256, zoom_max: 0x4000,
+        digital_zoom_max: 0x7AC0 },
+    Profile { name: "sony", version: [0x00,0x01, 0x05,0x19, 0x01,0x00, 0x02],
+        max_speed: (0x18, 0x17), presets: 256, zoom_max: 0x4000,
+        digital_zoom_max: 0x7AC0 },
+    Profile { name: "ptzoptics", version: [0x00,0x01, 0x05,0x0B, 0x01,0x00, 0x02],
+        max_speed: (0x18, 0x14), presets: 255, zoom_max: 0x4000,
+        digital_zoom_max: 0x7AC0 },
+    Profile { name: "lumens", version: [0x00,0x09, 0x00,0x01, 0x01,0x00, 0x02],
+        max_speed: (0x18, 0x14), presets: 128, zoom_max: 0x4000,
+        digital_zoom_max: 0x7AC0 },
+    Profile { name: "panasonic", version: [0x00,0x02, 0x00,0x01, 0x01,0x00, 0x02],
+        max_speed: (0x18, 0x18), presets: 100, zoom_max: 0x4000,
+        digital_zoom_max: 0x7AC0 },
+];
+
+impl Profile {
+    // config.txt: profile = sony, or profile.2 = sony only for camera 2,
+    // version, max_pan_speed, max_tilt_speed, presets, zoom_max and digital_zoom_max change the profile
+    fn of_camera(ncam: u8) -> Profile {
+        let cfg = config::settings();
+        let key = |k: &str| cfg.cam_key(k, ncam);
+        let name = cfg.get(&key("profile")).unwrap_or("generic");
+        let mut profile = match PROFILES.iter().find(|p| p.name == name) {
+            Some(p) => p.clone(),
+            None => {
+                eprintln!("config.txt: unknown profile {}", name);
+                PROFILES[0].clone()
+            }
+        };
+        if let Some(version) = cfg.get_bytes(&key("version")) {
+            match version.try_into() {
+                Ok(version) => profile.version = version,
+                Err(_v) => eprintln!("config.txt: version needs 7 bytes")
+            }
+        }
+        if let Some(pan) = cfg.get_u32(&key("max_pan_speed")) {
+            profile.max_speed.0 = pan.clamp(1, 0x18) as u8;
+        }
+        if let Some(tilt) = cfg.get_u32(&key("max_tilt_speed")) {
+            profile.max_speed.1 = tilt.clamp(1, 0x18) as u8;
+        }
+        if let Some(presets) = cfg.get_u32(&key("presets")) {
+            profile.presets = presets.clamp(1, 256) as u16;
+        }
+        if let Some(zoom_max) = cfg.get_u32(&key("zoom_max")) {
+            profile.zoom_max = zoom_max.clamp(1, 0xFFFE) as i64;
+        }
+        if let Some(digital_zoom_max) = cfg.get_u32(&key("digital_zoom_max")) {
+            profile.digital_zoom_max = digital_zoom_max.clamp(1, 0xFFFF) as i64;
+        }
+        profile.digital_zoom_max = std::cmp::max(profile.digital_zoom_max, profile.zoom_max+1);
+        profile
+    }
+    // speeds of the controller -> the scale of pan_speed_to_sec and tilt_speed_to_sec
+    fn pan_speed(&self, pan_speed: u8) -> u8 {
+        scale_speed(pan_speed, self.max_speed.0, 0x18)
+    }
+    fn tilt_speed(&self, tilt_speed: u8) -> u8 {
+        scale_speed(tilt_speed, self.max_speed.1, 0x14)
+    }
+}
+
+// config.txt: visca_address = 2, or visca_address.2 = 2 only for camera 2,
+// the port of the camera answers only that address instead of any of them
+fn visca_address(ncam: u8) -> Option<u8> {
+    let cfg = config::settings();
+    match cfg.get_u32(&cfg.cam_key("visca_address", ncam)) {
+        Some(address @ 1..=7) => Some(address as u8),
+        Some(address) => {
+            eprintln!("config.txt: visca_address {} out of 1-7", address);
+            None
+        },
+        None => None
+    }
+}
+
+// completion of a command running on a socket: socket, command id, result
+type CmdDone = (u8, u64, Result<(), UVIError>);
+
+// same ranges as CAM_Zoom Direct and CAM_Focus Direct
+// the optical zoom up to zoom_max, then the digital zoom, when the camera has both
+fn zoom_to_visca(zoom: f64, optical: Option<f64>, profile: &Profile) -> i64 {
+    let digital = (profile.digital_zoom_max-profile.zoom_max) as f64;
+    match optical {
+        Some(o) if zoom > o => profile.zoom_max + ((zoom-o)/(1.0-o)*digital).round() as i64,
+        Some(o) => (zoom/o*(profile.zoom_max as f64)).round() as i64,
+        None => (zoom*(profile.zoom_max as f64)).round() as i64
+    }
+}
+fn visca_to_zoom(zoom: i64, optical: Option<f64>, profile: &Profile) -> f64 {
+    let digital = (profile.digital_zoom_max-profile.zoom_max) as f64;
+    match optical {
+        Some(o) if zoom > profile.zoom_max =>
+            o + (1.0-o)*((std::cmp::min(zoom, profile.digital_zoom_max)-profile.zoom_max) as f64)/digital,
+        Some(o) => o*(zoom as f64)/(profile.zoom_max as f64),
+        None => (std::cmp::min(zoom, profile.zoom_max) as f64)/(profile.zoom_max as f64)
+    }
+}
+fn focus_to_visca(focus: f64) -> i64 {
+    (focus*(0xF000 as f64)).round() as i64
+}
+
+// block inquiries, fields missing on the camera take the defaults below
+const DEFAULT_RB_GAIN: u8 = 0x80;   // middle of 00-FF
+const DEFAULT_AE_MODE: u8 = 0x00;   // Full Auto
+const DEFAULT_SHUTTER: u8 = 0x0A;   // positions in the middle of their tables
+const DEFAULT_IRIS: u8 = 0x08;      // UVC cameras have no iris, always there
+const DEFAULT_BRIGHT: u8 = 0x0C;
+const DEFAULT_EXP_COMP: u8 = 0x07;  // 0 dB
+const DEFAULT_AF_SENSITIVITY: u8 = 0x02; // Normal
+const DEFAULT_COLOR_GAIN: u8 = 0x04; // 100%
+const DEFAULT_APERTURE: u8 = 0x00;
+const DEFAULT_GAIN_LIMIT: u8 = 0x0F;
+
+// CAM_Shutter positions: 1/x seconds
+const SHUTTER_SPEEDS: [f64; 0x16] = [1.0, 2.0, 4.0, 8.0, 15.0, 30.0, 60.0, 90.0, 100.0, 125.0, 180.0,
+    250.0, 350.0, 500.0, 725.0, 1000.0, 1500.0, 2000.0, 3000.0, 4000.0, 6000.0, 10000.0];
+const GAIN_MAX_POS: u8 = 0x0F;
+const BRIGHT_MAX_POS: u8 = 0x1F;
+const EXP_COMP_MAX_POS: u8 = 0x0E; // 07: 0 dB
+
+// image positions over the range of the camera controls
+const APERTURE_MAX_POS: u8 = 0x0F;
+const COLOR_GAIN_MAX_POS: u8 = 0x0E;
+const CONTRAST_MAX_POS: u8 = 0xFF;
+const GAMMA_MAX_POS: u8 = 0x04;
+const RB_GAIN_MAX_POS: u8 = 0xFF;
+
+fn pos_to_fraction(pos: u8, max: u8) -> f64 {
+    (std::cmp::min(pos, max) as f64)/(max as f64)
+}
+fn fraction_to_pos(f: f64, max: u8) -> u8 {
+    (f*(max as f64)).round().clamp(0.0, max as f64) as u8
 }
 
-fn nibbles_to_int(nibbles: &[u8]) -> i64 {
-    let mut r: i64 = 0;
-    for i in 0..nibbles.len() {
-        r = (r<<4) + (nibbles[i] as i64);
+fn exposure_max_pos(e: visca::Exposure) -> u8 {
+    match e {
+        visca::Exposure::Shutter => (SHUTTER_SPEEDS.len()-1) as u8,
+        visca::Exposure::Iris => DEFAULT_IRIS,
+        visca::Exposure::Gain => GAIN_MAX_POS,
+        visca::Exposure::Bright => BRIGHT_MAX_POS,
+        visca::Exposure::ExpComp => EXP_COMP_MAX_POS,
     }
-    if r & (0x8<<(4*nibbles.len()-4)) != 0 {
-        r -= 0x1<<(4*nibbles.len());
+}
+
+// current position, None when the camera does not have it
+fn exposure_pos(e: visca::Exposure, status: &protos::CamStatus) -> Option<u8> {
+    match e {
+        visca::Exposure::Shutter => status.shutter.map(|secs| {
+            // nearest speed on a log scale
+            let dist = |x: f64| (secs*x).ln().abs();
+            (0..SHUTTER_SPEEDS.len()).min_by(|a, b|
+                dist(SHUTTER_SPEEDS[*a]).total_cmp(&dist(SHUTTER_SPEEDS[*b]))).unwrap_or(0) as u8
+        }),
+        visca::Exposure::Iris => None,
+        visca::Exposure::Gain => status.gain.map(|g| fraction_to_pos(g, GAIN_MAX_POS)),
+        visca::Exposure::Bright => status.bright.map(|b| fraction_to_pos(b, BRIGHT_MAX_POS)),
+        visca::Exposure::ExpComp => status.exp_comp.map(|c| fraction_to_pos((c+1.0)/2.0, EXP_COMP_MAX_POS)),
     }
-    return r;
 }
 
-fn nibbles_to_sec_angle(nibbles: &[u8]) -> i64 {
-    return nibbles_to_int(nibbles)*36000/2359;
+fn rb_gain_to_visca(gain: Option<f64>) -> i64 {
+    gain.map(|g| fraction_to_pos(g, RB_GAIN_MAX_POS) as i64).unwrap_or(DEFAULT_RB_GAIN as i64)
 }
 
-fn list_to_hex(l: &[u8]) -> String {
-    let mut f = "".to_string();
-    for i in 0..l.len() {
-        f.push_str(&format!("{:02X} ", l[i]));
+fn lens_block(status: &protos::CamStatus, profile: &Profile) -> Vec<u8> {
+    //y0 50 0u 0u 0u 0u 00 00 0v 0v 0v 0v 00 0w 00 FF
+    //uuuu: Zoom Position
+    //vvvv: Focus Position
+    //w.bit0: Focus Mode 1: Auto 0: Manual
+    let mut v = Vec::new();
+    v.extend(visca::int_to_nibbles(zoom_to_visca(status.zoom.unwrap_or(0.0), status.zoom_optical, profile), 4));
+    v.extend([0u8, 0u8]);
+    v.extend(visca::int_to_nibbles(focus_to_visca(status.focus.unwrap_or(0.0)), 4));
+    v.extend([0u8]);
+    v.extend([if status.focus_auto.unwrap_or(false) {1u8} else {0u8}]);
+    v.extend([0u8]);
+    v
+}
+
+fn camera_block(status: &protos::CamStatus) -> Vec<u8> {
+    //y0 50 0p 0p 0q 0q 0r 0s tt 0u vv ww 00 xx 0z FF
+    //pp: R_Gain
+    //qq: B_Gain
+    //r: WB Mode
+    //s: Aperture
+    //tt: AE Mode
+    //u.bit2: Back Light
+    //u.bit1: Exposure Comp.
+    //vv: Shutter Position
+    //ww: Iris Position
+    //xx: Bright Position
+    //z: Exposure Comp. Position
+    let mut v = Vec::new();
+    v.extend(visca::int_to_nibbles(rb_gain_to_visca(status.red_gain), 2));
+    v.extend(visca::int_to_nibbles(rb_gain_to_visca(status.blue_gain), 2));
+    v.extend([status.wb_mode.unwrap_or(0) & 0xF]);
+    v.extend([status.sharpness.map_or(DEFAULT_APERTURE, |s| fraction_to_pos(s, APERTURE_MAX_POS))]);
+    v.extend([status.ae_mode.unwrap_or(DEFAULT_AE_MODE)]);
+    v.extend([(if status.backlight.unwrap_or(false) {0x04u8} else {0u8}) |
+        (if status.exp_comp_on.unwrap_or(false) {0x02u8} else {0u8})]);
+    v.extend([exposure_pos(visca::Exposure::Shutter, status).unwrap_or(DEFAULT_SHUTTER)]);
+    v.extend([DEFAULT_IRIS]);
+    v.extend([0u8]);
+    v.extend([exposure_pos(visca::Exposure::Bright, status).unwrap_or(DEFAULT_BRIGHT)]);
+    v.extend([exposure_pos(visca::Exposure::ExpComp, status).unwrap_or(DEFAULT_EXP_COMP)]);
+    v
+}
+
+fn enlargement_block(status: &protos::CamStatus) -> Vec<u8> {
+    //y0 50 00 00 00 00 00 00 00 0p 0q rr 0s 0t 0u FF
+    //p: AF sensitivity
+    //q.bit0: Picture flip(1:On, 0:Off)
+    // rr.bit6~3: Color Gain(0h(60%) to Eh(200%))
+    //s: Flip(0: Off, 1:Flip-H, 2:Flip-V, 3:Flip-HV)
+    //t.bit2~0: NR2D Level
+    //u: Gain Limit
+    let hflip = status.hflip.unwrap_or(false);
+    let vflip = status.vflip.unwrap_or(false);
+    let mut v = vec![0u8; 7];
+    v.extend([status.af_low.map_or(DEFAULT_AF_SENSITIVITY, |low| if low {0x03} else {0x02})]);
+    v.extend([if hflip && vflip {1u8} else {0u8}]);
+    v.extend([status.saturation.map_or(DEFAULT_COLOR_GAIN, |s| fraction_to_pos(s, COLOR_GAIN_MAX_POS)) << 3]);
+    v.extend([(hflip as u8) | ((vflip as u8) << 1)]);
+    v.extend([0u8]);
+    v.extend([DEFAULT_GAIN_LIMIT]);
+    v
+}
+
+// VISCA error reply for a command the camera couldn't do
+fn visca_error(e: &UVIError) -> ViscaErrorCode {
+    match e {
+        UVIError::CommandCancelled => ViscaErrorCode::Cancelled,
+        // missing control (e.g. no focus), unsupported mode, no preset database...
+        _ => ViscaErrorCode::NotExecutable
     }
-    return f;
+}
+
+struct RunningCmd {
+    id: u64,
+    seq: u32, // VISCA over IP sequence number of the command
+    cancel: Option<oneshot::Sender<()>>
 }
 
 struct ViscaIpCon {
     ncam: u8,
-    stream: TcpStream,
+    address: Option<u8>, // VISCA address of the camera for the controller, None: any of 1-7
+    reply_hdr: u8, // y0 of the address of the last message
+    peer: net::SocketAddr,
     main_chan: mpsc::Sender<protos::MainEvent>,
     cam_chan: mpsc::UnboundedSender<protos::CamCmd>,
+    profile: Profile,
+    preset_speed: Option<protos::PanTilt>, // of the last PresetSpeed, recorded with the next presets
+    seq: u32, // VISCA over IP sequence number of the message being processed
+    sockets: [Option<RunningCmd>; 2], // commands running on sockets 1 and 2
+    next_id: u64,
+    send_done: mpsc::UnboundedSender<CmdDone>,
+    recv_done: mpsc::UnboundedReceiver<CmdDone>,
+    outbox: Vec<(u32, Vec<u8>)> // sequence number, reply
+}
+
+struct ViscaTcpCon {
+    con: ViscaIpCon,
+    stream: TcpStream,
     recvkill: broadcast::Receiver<()>
 }
 
-impl ViscaIpCon {
-    async fn send_to_cam(&self, cmd: protos::CamCmd) -> Result<(), UVIError> {
-        self.cam_chan.send(cmd).map_err(|_x| UVIError::AsyncChannelClosed)
-    }
-    async fn send_datagram(&mut self, dg: &[u8]) {
-        let mut buf = vec![0x91u8];
-        buf.extend_from_slice(dg);
-        buf.push(0xff);
-        self.stream.write_all(&buf).await.unwrap();
-    }
+struct ViscaUdpCon {
+    con: ViscaIpCon,
+    socket: Arc<UdpSocket>,
+    recv_dg: mpsc::Receiver<Vec<u8>>
+}
+
+// one camera of the shared port, fed by the connection of the controller
+struct ViscaChainCon {
+    con: ViscaIpCon,
+    recv_pkt: mpsc::Receiver<Vec<u8>>,
+    send_out: mpsc::Sender<Vec<u8>>
+}
+
+// cameras of the shared port, at consecutive VISCA addresses in this order
+#[derive(Clone)]
+pub struct ViscaChain {
+    cams: Arc<Mutex<BTreeMap<u8, mpsc::UnboundedSender<protos::CamCmd>>>>
+}
+
+// controller connected to the shared port
+struct ViscaChainLink {
+    chain: ViscaChain,
+    peer: net::SocketAddr,
+    main_chan: mpsc::Sender<protos::MainEvent>,
+    stream: TcpStream,
+    first: u8, // address of the first camera, changed by Address Set
+    cons: HashMap<(u8, u8), mpsc::Sender<Vec<u8>>>, // ncam, address
+    send_out: mpsc::Sender<Vec<u8>>,
+    recv_out: mpsc::Receiver<Vec<u8>>
+}
+
+impl ViscaTcpCon {
     async fn process(&mut self) -> Result<(), UVIError> {
-        self.main_chan.send(protos::MainEvent::NewViscaConnection(self.ncam, 
-            self.stream.peer_addr()?)).await.map_err(|_x| UVIError::AsyncChannelClosed)?;
+        self.con.main_chan.send(protos::MainEvent::NewViscaConnection(self.con.ncam, 
+            self.con.peer)).await.map_err(|_x| UVIError::AsyncChannelClosed)?;
         let mut buf = Vec::new();
         let mut buf2 = vec![0u8;256];
         loop {
@@ -77,6 +401,9 @@ impl ViscaIpCon {
                 _ = self.recvkill.recv() => {
                     break;
                 },
+                Some((socket, id, res)) = self.con.recv_done.recv() => {
+                    self.con.done_received(socket, id, res);
+                },
                 read = self.stream.read(&mut buf2) => {
                     let n = match read {
                         Ok(n) if n == 0 => break,
@@ -87,7 +414,7 @@ impl ViscaIpCon {
                     let mut i = 0;
                     for p in 0..buf.len() {
                         if buf[p] == 0xFFu8 {
-                            self.data_received(&buf[i..p]).await?;
+                            self.con.data_received(&buf[i..=p]).await?;
                             i = p+1;
                         }
                     }
@@ -97,174 +424,717 @@ impl ViscaIpCon {
                     }        
                 }
             }
+            for (_seq, reply) in self.con.outbox.drain(..) {
+                self.stream.write_all(&reply).await?;
+            }
+        }
+        self.con.main_chan.send(protos::MainEvent::LostViscaConnection(self.con.ncam, 
+            self.con.peer)).await.map_err(|_x| UVIError::AsyncChannelClosed)?;
+        Ok(())
+    }
+}
+
+impl ViscaUdpCon {
+    async fn send_voip(&self, typ: u16, seq: u32, payload: &[u8]) -> Result<(), UVIError> {
+        let mut buf = Vec::with_capacity(8+payload.len());
+        buf.extend_from_slice(&typ.to_be_bytes());
+        buf.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+        buf.extend_from_slice(&seq.to_be_bytes());
+        buf.extend_from_slice(payload);
+        self.socket.send_to(&buf, self.con.peer).await?;
+        Ok(())
+    }
+    async fn datagram_received(&mut self, dg: &[u8]) -> Result<(), UVIError> {
+        if dg.len() < 8 { return Ok(()); } // Ignore datagrams without a full header
+        let typ = u16::from_be_bytes([dg[0], dg[1]]);
+        let len = u16::from_be_bytes([dg[2], dg[3]]) as usize;
+        let seq = u32::from_be_bytes([dg[4], dg[5], dg[6], dg[7]]);
+        let payload = &dg[8..];
+        if len != payload.len() {
+            return self.send_voip(VOIP_CONTROL_REPLY, seq, &[0x0F, 0x02]).await; // abnormality in message
+        }
+        match typ {
+            VOIP_VISCA_COMMAND | VOIP_VISCA_INQUIRY | VOIP_VISCA_DEVICE_SETTING => {
+                self.con.seq = seq;
+                let mut i = 0;
+                for p in 0..payload.len() {
+                    if payload[p] == 0xFFu8 {
+                        self.con.data_received(&payload[i..=p]).await?;
+                        i = p+1;
+                    }
+                }
+            },
+            VOIP_CONTROL_COMMAND => {
+                if payload.len() == 1 && payload[0] == 0x01 { // RESET: sequence number back to 0
+                    self.send_voip(VOIP_CONTROL_REPLY, seq, &[0x01]).await?;
+                } else if payload.len() >= 1 && payload[0] == 0x0F {
+                    // error notification from the controller: nothing to do
+                } else {
+                    self.send_voip(VOIP_CONTROL_REPLY, seq, &[0x0F, 0x02]).await?;
+                }
+            },
+            _ => { // the reply tells the controller, nothing printed for every packet
+                self.send_voip(VOIP_CONTROL_REPLY, seq, &[0x0F, 0x02]).await?;
+            }
+        }
+        Ok(())
+    }
+    async fn process(&mut self) -> Result<(), UVIError> {
+        self.con.main_chan.send(protos::MainEvent::NewViscaConnection(self.con.ncam, 
+            self.con.peer)).await.map_err(|_x| UVIError::AsyncChannelClosed)?;
+        loop {
+            let until = Instant::now() + UDP_PEER_TIMEOUT;
+            tokio::select! {
+                _ = sleep_until(until) => {
+                    break;
+                },
+                Some((socket, id, res)) = self.con.recv_done.recv() => {
+                    self.con.done_received(socket, id, res);
+                },
+                dg = self.recv_dg.recv() => {
+                    match dg {
+                        Some(dg) => self.datagram_received(&dg).await?,
+                        None => break
+                    }
+                }
+            }
+            let replies: Vec<(u32, Vec<u8>)> = self.con.outbox.drain(..).collect();
+            for (seq, reply) in replies {
+                self.send_voip(VOIP_VISCA_REPLY, seq, &reply).await?;
+            }
+        }
+        self.con.main_chan.send(protos::MainEvent::LostViscaConnection(self.con.ncam, 
+            self.con.peer)).await.map_err(|_x| UVIError::AsyncChannelClosed)?;
+        Ok(())
+    }
+}
+
+impl ViscaChainCon {
+    async fn process(&mut self) -> Result<(), UVIError> {
+        self.con.main_chan.send(protos::MainEvent::NewViscaConnection(self.con.ncam, 
+            self.con.peer)).await.map_err(|_x| UVIError::AsyncChannelClosed)?;
+        loop {
+            tokio::select! {
+                Some((socket, id, res)) = self.con.recv_done.recv() => {
+                    self.con.done_received(socket, id, res);
+                },
+                pkt = self.recv_pkt.recv() => {
+                    match pkt {
+                        Some(pkt) if pkt[0] == visca::BROADCAST => self.con.clear_sockets(), // IF_Clear
+                        Some(pkt) => self.con.data_received(&pkt).await?,
+                        None => break
+                    }
+                }
+            }
+            for (_seq, reply) in self.con.outbox.drain(..) {
+                if self.send_out.send(reply).await.is_err() { break; }
+            }
+        }
+        self.con.main_chan.send(protos::MainEvent::LostViscaConnection(self.con.ncam, 
+            self.con.peer)).await.map_err(|_x| UVIError::AsyncChannelClosed)?;
+        Ok(())
+    }
+}
+
+impl ViscaChain {
+    pub fn new() -> ViscaChain {
+        ViscaChain { cams: Arc::new(Mutex::new(BTreeMap::new())) }
+    }
+    pub fn add(&self, ncam: u8, cam_chan: mpsc::UnboundedSender<protos::CamCmd>) {
+        self.cams.lock().unwrap().insert(ncam, cam_chan);
+    }
+    // the cameras still alive
+    fn cams(&self) -> Vec<(u8, mpsc::UnboundedSender<protos::CamCmd>)> {
+        let mut cams = self.cams.lock().unwrap();
+        cams.retain(|_, cam_chan| !cam_chan.is_closed());
+        cams.iter().map(|(ncam, cam_chan)| (*ncam, cam_chan.clone())).collect()
+    }
+}
+
+impl ViscaChainLink {
+    async fn process(&mut self) -> Result<(), UVIError> {
+        let mut buf = Vec::new();
+        let mut buf2 = vec![0u8;256];
+        loop {
+            tokio::select! {
+                Some(reply) = self.recv_out.recv() => {
+                    self.stream.write_all(&reply).await?;
+                },
+                read = self.stream.read(&mut buf2) => {
+                    let n = match read {
+                        Ok(n) if n == 0 => break,
+                        Ok(n) => n,
+                        Err(_e) => break
+                    };
+                    buf.extend_from_slice(&mut buf2[..n]);
+                    let mut i = 0;
+                    for p in 0..buf.len() {
+                        if buf[p] == 0xFFu8 {
+                            let pkt = buf[i..=p].to_vec();
+                            self.packet_received(pkt).await?;
+                            i = p+1;
+                        }
+                    }
+                    buf = buf[i..].to_vec();
+                    if buf.len() > 200 {
+                        break;
+                    }
+                }
+            }
         }
-        self.main_chan.send(protos::MainEvent::LostViscaConnection(self.ncam, 
-            self.stream.peer_addr()?)).await.map_err(|_x| UVIError::AsyncChannelClosed)?;
         Ok(())
     }
+    async fn packet_received(&mut self, pkt: Vec<u8>) -> Result<(), UVIError> {
+        if pkt.len() < 2 || pkt[0] & 0xF0 != 0x80 { return Ok(()); }
+        if pkt[0] == visca::BROADCAST {
+            match visca::decode(&pkt) {
+                Ok((_hdr, ViscaMessage::AddressSet(first))) => {
+                    // the cameras are renumbered: the next packets go to new connections
+                    self.first = first;
+                    self.cons.clear();
+                    let next = first + std::cmp::min(self.chain.cams().len(), (8-first) as usize) as u8;
+                    let reply = visca::encode(visca::BROADCAST, &ViscaMessage::AddressSet(next));
+                    self.stream.write_all(&reply).await?;
+                },
+                Ok((_hdr, ViscaMessage::Command(ViscaCommand::IfClear()))) => {
+                    for send_pkt in self.cons.values() {
+                        send_pkt.send(pkt.clone()).await.ok();
+                    }
+                    self.stream.write_all(&pkt).await?; // passed along the chain back to the controller
+                },
+                _ => ()
+            }
+            return Ok(());
+        }
+        let address = pkt[0] & 0x0F;
+        if address < self.first { return Ok(()); }
+        let (ncam, cam_chan) = match self.chain.cams().into_iter().nth((address - self.first) as usize) {
+            Some(cam) => cam,
+            None => return Ok(()) // no camera at that address
+        };
+        self.cons.retain(|_, send_pkt| !send_pkt.is_closed());
+        if let Some(send_pkt) = self.cons.get(&(ncam, address)) {
+            if send_pkt.send(pkt.clone()).await.is_ok() { return Ok(()); }
+        }
+        let (send_pkt, recv_pkt) = mpsc::channel(100);
+        send_pkt.send(pkt).await.ok();
+        self.cons.insert((ncam, address), send_pkt);
+        let mut con = ViscaIpCon::new(ncam, self.peer, self.main_chan.clone(), cam_chan);
+        con.address = Some(address);
+        let mut v = ViscaChainCon {
+            con: con,
+            recv_pkt: recv_pkt,
+            send_out: self.send_out.clone()
+        };
+        task::spawn(async move {
+            match v.process().await {
+                Err(e) => {
+                    eprintln!("Closing VISCA shared port camera for error: {}", e);
+                }
+                _ => ()
+            }
+        });
+        Ok(())
+    }
+}
+
+impl ViscaIpCon {
+    fn new(ncam: u8, peer: net::SocketAddr, main_chan: mpsc::Sender<protos::MainEvent>,
+            cam_chan: mpsc::UnboundedSender<protos::CamCmd>) -> ViscaIpCon {
+        let (send_done, recv_done) = mpsc::unbounded_channel();
+        ViscaIpCon {
+            ncam: ncam,
+            address: visca_address(ncam),
+            reply_hdr: visca::header(1, 0),
+            profile: Profile::of_camera(ncam),
+            preset_speed: None,
+            peer: peer,
+            main_chan: main_chan,
+            cam_chan: cam_chan,
+            seq: 0,
+            sockets: [None, None],
+            next_id: 0,
+            send_done: send_done,
+            recv_done: recv_done,
+            outbox: Vec::new()
+        }
+    }
+    async fn send_to_cam(&self, cmd: protos::CamCmd) -> Result<(), UVIError> {
+        self.cam_chan.send(cmd).map_err(|_x| UVIError::AsyncChannelClosed)
+    }
+    async fn query_status(&self) -> Result<protos::CamStatus, UVIError> {
+        let (s, r) = oneshot::channel();
+        self.send_to_cam(protos::CamCmd::QueryStatus(s)).await?;
+        r.await.map_err(|_x| UVIError::AsyncChannelNoSender)?
+    }
+    // pos None: reset to the default of the camera
+    async fn send_exposure(&self, e: visca::Exposure, pos: Option<u8>, s: protos::Completion) -> Result<(), UVIError> {
+        let pos = pos.map(|p| std::cmp::min(p, exposure_max_pos(e)));
+        let cmd = match e {
+            visca::Exposure::Shutter => protos::CamCmd::Shutter(pos.map(|p| 1.0/SHUTTER_SPEEDS[p as usize]), s),
+            visca::Exposure::Iris => {
+                s.send(Err(UVIError::CommandNotSupported));
+                return Ok(());
+            },
+            visca::Exposure::Gain => protos::CamCmd::Gain(pos.map(|p| (p as f64)/(GAIN_MAX_POS as f64)), s),
+            visca::Exposure::Bright => protos::CamCmd::Brightness(pos.map(|p| (p as f64)/(BRIGHT_MAX_POS as f64)), s),
+            visca::Exposure::ExpComp => protos::CamCmd::ExposureCompensation(
+                pos.map_or(0.0, |p| 2.0*(p as f64)/(EXP_COMP_MAX_POS as f64) - 1.0), s),
+        };
+        self.send_to_cam(cmd).await
+    }
+    fn send_reply_seq(&mut self, seq: u32, reply: &ViscaReply) {
+        self.outbox.push((seq, visca::encode_reply(self.reply_hdr, reply)));
+    }
+    fn send_reply(&mut self, reply: &ViscaReply) {
+        self.send_reply_seq(self.seq, reply);
+    }
+
+    // cancel the running commands, their completions are not reported anymore
+    fn clear_sockets(&mut self) {
+        for sock in self.sockets.iter_mut() {
+            if let Some(RunningCmd {cancel: Some(cancel), ..}) = sock.take() {
+                cancel.send(()).ok();
+            }
+        }
+    }
 
     async fn data_received(&mut self, dg: &[u8]) -> Result<(), UVIError> {
-        if dg.len() < 2 { return Ok(()); } // Ignore messages that are too short
-        if dg[0] != 0x81 { return Ok(()); } // Ignore messages not addressed properly
-        if dg[1] == 0x01 { // Command
-            if dg[2] == 0x04 && dg[3] == 0x3f { // Cam Memory
-                if dg[4] == 0x00 { // print('reset preset %d' % dg[5])
-                    self.send_to_cam(protos::CamCmd::ResetPreset(dg[5])).await?;
-                } else if dg[4] == 0x01 { // print('save preset %d' % dg[5])
-                    self.send_to_cam(protos::CamCmd::RecordPreset(dg[5])).await?;
-                } else if dg[4] == 0x02 { // print('recall preset %d' % dg[5])
-                    self.send_to_cam(protos::CamCmd::RecoverPreset(dg[5])).await?;
+        // Ignore messages not addressed properly: 8x, x the address of this camera
+        if dg.len() < 1 || dg[0] & 0xF0 != 0x80 { return Ok(()); }
+        let address = dg[0] & 0x0F;
+        if address < 1 || address > 7 || self.address.map_or(false, |a| a != address) { return Ok(()); }
+        self.reply_hdr = visca::header(address, 0);
+        let msg = match visca::decode(dg) {
+            Ok((_hdr, msg)) => msg,
+            Err(_e) => {
+                self.send_reply(&ViscaReply::Error(0, ViscaErrorCode::Syntax));
+                return Ok(());
+            }
+        };
+        match msg {
+            ViscaMessage::Command(ViscaCommand::IfClear()) => {
+                self.clear_sockets();
+                self.send_reply(&ViscaReply::Completion(0));
+            },
+            ViscaMessage::Command(cmd) => {
+                let socket = match self.sockets.iter().position(|s| s.is_none()) {
+                    Some(n) => (n+1) as u8,
+                    None => {
+                        self.send_reply(&ViscaReply::Error(0, ViscaErrorCode::BufferFull));
+                        return Ok(());
+                    }
+                };
+                let (done, cancel) = self.command_received(cmd).await?;
+                self.send_reply(&ViscaReply::Ack(socket));
+                let id = self.next_id;
+                self.next_id += 1;
+                self.sockets[(socket-1) as usize] = Some(RunningCmd {id: id, seq: self.seq, cancel: Some(cancel)});
+                let send_done = self.send_done.clone();
+                task::spawn(async move {
+                    let res = done.await.unwrap_or(Err(UVIError::AsyncChannelNoSender));
+                    send_done.send((socket, id, res)).ok();
+                });
+            },
+            ViscaMessage::Cancel(socket) => {
+                let running = match socket {
+                    1 | 2 => self.sockets[(socket-1) as usize].as_mut(),
+                    _ => None
+                };
+                match running.and_then(|cmd| cmd.cancel.take()) {
+                    // the Cancelled error is replied when the camera stops the command
+                    Some(cancel) => { cancel.send(()).ok(); },
+                    None => self.send_reply(&ViscaReply::Error(socket, ViscaErrorCode::NoSocket))
+                }
+            },
+            ViscaMessage::Inquiry(inq) => {
+                match self.inquiry_received(inq).await {
+                    Err(e) if !e.is_fatal() => self.send_reply(&ViscaReply::Error(0, visca_error(&e))),
+                    r => r?
+                }
+            },
+            ViscaMessage::AddressSet(_) => () // broadcast, never addressed to one camera
+        }
+        Ok(())
+    }
+
+    fn done_received(&mut self, socket: u8, id: u64, res: Result<(), UVIError>) {
+        match self.sockets[(socket-1) as usize] {
+            Some(RunningCmd {id: sid, seq, ..}) if sid == id => {
+                self.sockets[(socket-1) as usize] = None;
+                match res {
+                    Ok(()) => self.send_reply_seq(seq, &ViscaReply::Completion(socket)),
+                    Err(e) => self.send_reply_seq(seq, &ViscaReply::Error(socket, visca_error(&e)))
                 }
-            } else if dg[2] == 0x00 && dg[3] == 0x01 { // IF_Clear
-                // println!("Clearing command buffer");
-            } else if dg[2] == 0x06 && dg[3] == 0x04 { // Home
-                self.send_to_cam(protos::CamCmd::Home()).await?;
-            } else if dg[2] == 0x06 && dg[3] == 0x01 { // move cam pan/tilt
-                // seconds(degree/3600) per second
-                let mut panspeed = ((dg[4] as i64) % (0x18+1)) * 3600; // 0x18 -> ~ 5sec for 180 degrees
-                if dg[4] > 0x08 { panspeed = 2*panspeed; }
-                if dg[4] > 0x12 { panspeed = 2*panspeed; }
-                let tiltspeed = ((dg[5] as i64) % (0x14+1)) * 3600; // 0x14 -> ~2sec for 45 degrees
-                let panmove:i64 = panspeed * (if dg[6] == 1 {-1} else if dg[6] == 2 {1} else {0});
-                let tiltmove:i64 = tiltspeed * (if dg[7] == 1 {1} else if dg[7] == 2 {-1} else {0});
+            },
+            _ => () // socket cleared by IF_Clear
+        }
+    }
+
+    // R or B gain one step from the current one
+    async fn adjust_rb_gain(&self, red: bool, adjust: visca::Adjust, s: protos::Completion) -> Result<(), UVIError> {
+        let status = self.query_status().await?;
+        let pos = rb_gain_to_visca(if red {status.red_gain} else {status.blue_gain}) as u8;
+        let pos = match adjust {
+            visca::Adjust::Reset => DEFAULT_RB_GAIN,
+            visca::Adjust::Up => pos.saturating_add(1),
+            visca::Adjust::Down => pos.saturating_sub(1),
+        };
+        let gain = Some(pos_to_fraction(pos, RB_GAIN_MAX_POS));
+        let (r, b) = if red {(gain, None)} else {(None, gain)};
+        self.send_to_cam(protos::CamCmd::WhiteBalanceGain(r, b, s)).await
+    }
+    // returns the completion of the command to wait for and the way to cancel it
+    async fn command_received(&mut self, cmd: ViscaCommand) -> 
+            Result<(oneshot::Receiver<Result<(), UVIError>>, oneshot::Sender<()>), UVIError> {
+        let (s, r, cancel) = protos::Completion::new();
+        match cmd {
+            ViscaCommand::PresetReset(n) | ViscaCommand::PresetSet(n) | ViscaCommand::PresetRecall(n)
+                    if (n as u16) >= self.profile.presets => {
+                s.send(Err(UVIError::CommandNotSupported));
+            },
+            ViscaCommand::PresetReset(n) => {
+                self.send_to_cam(protos::CamCmd::ResetPreset(n, s)).await?;
+            },
+            ViscaCommand::PresetSet(n) => {
+                self.send_to_cam(protos::CamCmd::RecordPreset(n,
+                    self.preset_speed.as_ref().map(|sp| protos::PanTilt { pan: sp.pan, tilt: sp.tilt }), s)).await?;
+            },
+            ViscaCommand::PresetRecall(n) => {
+                self.send_to_cam(protos::CamCmd::RecoverPreset(n, s)).await?;
+            },
+            ViscaCommand::PresetSpeed(speed) => {
+                let speed = move_speed(self.profile.pan_speed(speed), self.profile.tilt_speed(speed));
+                self.preset_speed = Some(protos::PanTilt { pan: speed.pan, tilt: speed.tilt });
+                self.send_to_cam(protos::CamCmd::PresetSpeed(speed, s)).await?;
+            },
+            ViscaCommand::Tally(on) => {
+                self.main_chan.send(protos::MainEvent::Tally(self.ncam, on)).await.ok();
+                self.send_to_cam(protos::CamCmd::Tally(on, s)).await?;
+            },
+            ViscaCommand::IfClear() => { // handled with the sockets in data_received
+                s.send(Ok(()));
+            },
+            ViscaCommand::PanTiltHome() => {
+                self.send_to_cam(protos::CamCmd::Home(s)).await?;
+            },
+            ViscaCommand::PanTiltReset() => {
+                self.send_to_cam(protos::CamCmd::PanTiltReset(s)).await?;
+            },
+            ViscaCommand::PanTiltDrive { pan_speed, tilt_speed, pan_dir, tilt_dir } => {
+                let panspeed = pan_speed_to_sec(self.profile.pan_speed(pan_speed));
+                let tiltspeed = tilt_speed_to_sec(self.profile.tilt_speed(tilt_speed));
+                let panmove:i64 = panspeed * (if pan_dir == 1 {-1} else if pan_dir == 2 {1} else {0});
+                let tiltmove:i64 = tiltspeed * (if tilt_dir == 1 {1} else if tilt_dir == 2 {-1} else {0});
                 self.send_to_cam(protos::CamCmd::MoveContinuous(protos::PanTilt{
                     pan: panmove, tilt: tiltmove
-                })).await?;
-            } else if dg[2] == 0x06 && dg[3] == 0x03 { // move pan/tilt relative
-                let panmove = nibbles_to_sec_angle(&dg[6..10]);
-                let tiltmove = nibbles_to_sec_angle(&dg[10..14]);
+                }, s)).await?;
+            },
+            ViscaCommand::PanTiltRelative { pan_speed, tilt_speed, pan, tilt } => {
                 self.send_to_cam(protos::CamCmd::MoveRelative(protos::PanTilt{
-                    pan: panmove, tilt: tiltmove
-                })).await?;
-            } else if dg[2] == 0x06 && dg[3] == 0x02 { // move pan/tilt absolute
-                let panpos = nibbles_to_sec_angle(&dg[6..10]);
-                let tiltpos = nibbles_to_sec_angle(&dg[10..14]);
+                    pan: visca_to_sec_angle(pan), tilt: visca_to_sec_angle(tilt)
+                }, move_speed(self.profile.pan_speed(pan_speed), self.profile.tilt_speed(tilt_speed)), s)).await?;
+            },
+            ViscaCommand::PanTiltAbsolute { pan_speed, tilt_speed, pan, tilt } => {
                 self.send_to_cam(protos::CamCmd::MoveAbsolute(protos::PanTilt{
-                    pan: panpos, tilt: tiltpos
-                })).await?;
-            } else if dg[2] == 0x04 && dg[3] == 0x07 { // move cam zoom
-                let mut zoom:f64 = 0.0;
-                if dg[4] == 2 { zoom = 1.0; }
-                else if dg[4] == 3 { zoom = -1.0; }
-                else if dg[4] & 0xF0 == 0x20 { zoom = ((1+(dg[4] & 0x7)) as f64)/8.0; }
-                else if dg[4] & 0xF0 == 0x30 { zoom = -((1+(dg[4] & 0x7)) as f64)/8.0; }
-                self.send_to_cam(protos::CamCmd::ZoomContinuous(zoom)).await?;
-            } else if dg[2] == 0x04 && dg[3] == 0x47 { // move cam zoom direct
-                let zoom:f64 = (nibbles_to_int(&dg[4..8]) as f64)/(0x4000 as f64);
-                self.send_to_cam(protos::CamCmd::ZoomDirect(zoom)).await?;
-            } else if dg[2] == 0x04 && dg[3] == 0x38 { // focus mode
-                if dg[4] == 2 {
-                    self.send_to_cam(protos::CamCmd::AutoFocus(true)).await?;
-                } else if dg[4] == 3 {
-                    self.send_to_cam(protos::CamCmd::AutoFocus(false)).await?;
-                } else if dg[4] == 0x10 {
-                    self.send_to_cam(protos::CamCmd::AutoFocusToggle()).await?;
-                }
-            } else if dg[2] == 0x04 && dg[3] == 0x08 { // move cam focus
-                let mut focus:f64 = 0.0;
-                if dg[4] == 2 { focus = 1.0; }
-                else if dg[4] == 3 { focus = -1.0; }
-                else if dg[4] & 0xF0 == 0x20 { focus = ((1+(dg[4] & 0x7)) as f64)/8.0; }
-                else if dg[4] & 0xF0 == 0x30 { focus = -((1+(dg[4] & 0x7)) as f64)/8.0; }
-                self.send_to_cam(protos::CamCmd::FocusContinuous(focus)).await?;
-            } else if dg[2] == 0x04 && dg[3] == 0x48 { // move cam focus direct
+                    pan: visca_to_sec_angle(pan), tilt: visca_to_sec_angle(tilt)
+                }, move_speed(self.profile.pan_speed(pan_speed), self.profile.tilt_speed(tilt_speed)), s)).await?;
+            },
+            ViscaCommand::PanTiltLimitSet { corner, pan, tilt } => {
+                self.send_to_cam(protos::CamCmd::PanTiltLimitSet(corner, protos::PanTilt{
+                    pan: visca_to_sec_angle(pan), tilt: visca_to_sec_angle(tilt)
+                }, s)).await?;
+            },
+            ViscaCommand::PanTiltLimitClear(corner) => {
+                self.send_to_cam(protos::CamCmd::PanTiltLimitClear(corner, s)).await?;
+            },
+            ViscaCommand::Zoom(drive) => {
+                self.send_to_cam(protos::CamCmd::ZoomContinuous(lens_drive_speed(&drive), s)).await?;
+            },
+            ViscaCommand::ZoomDirect(zoom) => {
+                let optical = self.query_status().await?.zoom_optical;
+                let zoom = visca_to_zoom(zoom as i64, optical, &self.profile);
+                self.send_to_cam(protos::CamCmd::ZoomDirect(zoom, s)).await?;
+            },
+            ViscaCommand::ZoomFocusDirect(zoom, focus) => {
+                let optical = self.query_status().await?.zoom_optical;
+                let zoom = visca_to_zoom(zoom as i64, optical, &self.profile);
+                self.send_to_cam(protos::CamCmd::ZoomFocusDirect(zoom, (focus as f64)/(0xF000 as f64), s)).await?;
+            },
+            ViscaCommand::DigitalZoom(on) => {
+                self.send_to_cam(protos::CamCmd::DigitalZoom(on, s)).await?;
+            },
+            ViscaCommand::FocusMode(visca::FocusMode::Auto) => {
+                self.send_to_cam(protos::CamCmd::AutoFocus(true, s)).await?;
+            },
+            ViscaCommand::FocusMode(visca::FocusMode::Manual) => {
+                self.send_to_cam(protos::CamCmd::AutoFocus(false, s)).await?;
+            },
+            ViscaCommand::FocusMode(visca::FocusMode::Toggle) => {
+                self.send_to_cam(protos::CamCmd::AutoFocusToggle(s)).await?;
+            },
+            ViscaCommand::Focus(drive) => {
+                self.send_to_cam(protos::CamCmd::FocusContinuous(lens_drive_speed(&drive), s)).await?;
+            },
+            ViscaCommand::FocusDirect(focus) => {
                 // pppp: F000 (Near) - 0000 (Far) -> 1.0 (Near) - 0.0 (Far)
-                let focus:f64 = (nibbles_to_int(&dg[4..8]) as f64)/(0xF000 as f64);
-                self.send_to_cam(protos::CamCmd::FocusDirect(focus)).await?;
-            } else if dg[2] == 0x04 && dg[3] == 0x18 { // one push focus
-                if dg[4] == 1 {
-                    self.send_to_cam(protos::CamCmd::FocusOnePushTrigger()).await?;
-                } else if dg[4] == 2 {
-                    self.send_to_cam(protos::CamCmd::FocusDirect(0.0)).await?; // FAR (infinite)
+                self.send_to_cam(protos::CamCmd::FocusDirect((focus as f64)/(0xF000 as f64), s)).await?;
+            },
+            ViscaCommand::FocusOnePushTrigger() => {
+                self.send_to_cam(protos::CamCmd::FocusOnePushTrigger(s)).await?;
+            },
+            ViscaCommand::AfSensitivity(sens) => {
+                self.send_to_cam(protos::CamCmd::AfSensitivity(sens == 0x03, s)).await?;
+            },
+            ViscaCommand::AfMode(mode) => {
+                self.send_to_cam(protos::CamCmd::AfMode(mode, s)).await?;
+            },
+            ViscaCommand::AfIntervalTime(active, staying) => {
+                self.send_to_cam(protos::CamCmd::AfInterval(active, staying, s)).await?;
+            },
+            ViscaCommand::FocusInfinity() => {
+                self.send_to_cam(protos::CamCmd::FocusDirect(0.0, s)).await?; // FAR (infinite)
+            },
+            ViscaCommand::WhiteBalanceOnePushTrigger() => {
+                self.send_to_cam(protos::CamCmd::WhiteBalanceTrigger(s)).await?;
+            },
+            ViscaCommand::WhiteBalanceMode(mode) => {
+                self.send_to_cam(protos::CamCmd::WhiteBalanceMode(mode, s)).await?;
+            },
+            ViscaCommand::RGainDirect(pos) => {
+                let red = pos_to_fraction(pos, RB_GAIN_MAX_POS);
+                self.send_to_cam(protos::CamCmd::WhiteBalanceGain(Some(red), None, s)).await?;
+            },
+            ViscaCommand::BGainDirect(pos) => {
+                let blue = pos_to_fraction(pos, RB_GAIN_MAX_POS);
+                self.send_to_cam(protos::CamCmd::WhiteBalanceGain(None, Some(blue), s)).await?;
+            },
+            ViscaCommand::RGain(adjust) => {
+                self.adjust_rb_gain(true, adjust, s).await?;
+            },
+            ViscaCommand::BGain(adjust) => {
+                self.adjust_rb_gain(false, adjust, s).await?;
+            },
+            ViscaCommand::AeMode(mode) => {
+                self.send_to_cam(protos::CamCmd::AutoExposure(mode, s)).await?;
+            },
+            ViscaCommand::ExposureDirect(e, pos) => {
+                self.send_exposure(e, Some(pos), s).await?;
+            },
+            ViscaCommand::ExposureAdjust(e, visca::Adjust::Reset) => {
+                self.send_exposure(e, None, s).await?;
+            },
+            ViscaCommand::ExposureAdjust(e, adjust) => {
+                match exposure_pos(e, &self.query_status().await?) {
+                    Some(pos) => {
+                        let pos = if adjust == visca::Adjust::Up {pos.saturating_add(1)} else {pos.saturating_sub(1)};
+                        self.send_exposure(e, Some(pos), s).await?;
+                    },
+                    None if e == visca::Exposure::Iris => s.send(Err(UVIError::CommandNotSupported)),
+                    None => s.send(Err(UVIError::CamControlNotFound))
                 }
-            } else if dg[2] == 0x04 && dg[3] == 0x10 { // one push wb
-                if dg[4] == 0x05 {
-                    self.send_to_cam(protos::CamCmd::WhiteBalanceTrigger()).await?;
+            },
+            ViscaCommand::ExpCompMode(on) => {
+                self.send_to_cam(protos::CamCmd::ExposureCompensationOn(on, s)).await?;
+            },
+            ViscaCommand::Backlight(on) => {
+                self.send_to_cam(protos::CamCmd::Backlight(on, s)).await?;
+            },
+            ViscaCommand::LrReverse(on) => {
+                self.send_to_cam(protos::CamCmd::Flip(Some(on), None, s)).await?;
+            },
+            ViscaCommand::PictureFlip(on) => { // upside down, ceiling mount
+                self.send_to_cam(protos::CamCmd::Flip(Some(on), Some(on), s)).await?;
+            },
+            ViscaCommand::Flip(f) => {
+                self.send_to_cam(protos::CamCmd::Flip(Some(f & 1 != 0), Some(f & 2 != 0), s)).await?;
+            },
+            ViscaCommand::ApertureAdjust(visca::Adjust::Reset) => {
+                self.send_to_cam(protos::CamCmd::Sharpness(None, s)).await?;
+            },
+            ViscaCommand::ApertureAdjust(adjust) => {
+                match self.query_status().await?.sharpness {
+                    Some(sharpness) => {
+                        let pos = fraction_to_pos(sharpness, APERTURE_MAX_POS);
+                        let pos = if adjust == visca::Adjust::Up {pos.saturating_add(1)} else {pos.saturating_sub(1)};
+                        self.send_to_cam(protos::CamCmd::Sharpness(Some(pos_to_fraction(pos, APERTURE_MAX_POS)), s)).await?;
+                    },
+                    None => s.send(Err(UVIError::CamControlNotFound))
                 }
-            } else if dg[2] == 0x04 && dg[3] == 0x35 { // set white balance
-                self.send_to_cam(protos::CamCmd::WhiteBalanceMode(dg[4])).await?;
-            } else {
-                println!("command unknown: {}", list_to_hex(&dg));
-            }
-            self.send_datagram(&[0x41u8]).await;
-            self.send_datagram(&[0x51u8]).await;
+            },
+            ViscaCommand::ApertureDirect(pos) => {
+                self.send_to_cam(protos::CamCmd::Sharpness(Some(pos_to_fraction(pos, APERTURE_MAX_POS)), s)).await?;
+            },
+            ViscaCommand::ColorGain(pos) => {
+                self.send_to_cam(protos::CamCmd::Saturation(pos_to_fraction(pos, COLOR_GAIN_MAX_POS), s)).await?;
+            },
+            ViscaCommand::Contrast(pos) => {
+                self.send_to_cam(protos::CamCmd::Contrast(pos_to_fraction(pos, CONTRAST_MAX_POS), s)).await?;
+            },
+            ViscaCommand::Gamma(pos) => {
+                self.send_to_cam(protos::CamCmd::Gamma(pos_to_fraction(pos, GAMMA_MAX_POS), s)).await?;
+            },
         }
-        else if dg[1] == 0x09 { // Inquiry
-            if dg[2] == 0x00 && dg[3] == 0x02 { // CAM_VersionInq
-                // print('CAM_VersionInq')
-                self.send_datagram(&[0x50u8, 0x09,0x99, 0x00,0x01, 0x00,0x01, 0x02]).await;
-            } else if dg[2] == 0x06 && dg[3] == 0x12 { // Pan-tiltPosInq
+        Ok((r, cancel))
+    }
+
+    async fn inquiry_received(&mut self, inq: ViscaInquiry) -> Result<(), UVIError> {
+        match inq {
+            ViscaInquiry::Version() => { // CAM_VersionInq
+                self.send_reply(&ViscaReply::InquiryData(self.profile.version.to_vec()));
+            },
+            ViscaInquiry::Preset() => { // CAM_MemoryInq
+                let (s, r) = oneshot::channel();
+                self.send_to_cam(protos::CamCmd::QueryPreset(s)).await?;
+                let preset = r.await.map_err(|_x| UVIError::AsyncChannelNoSender)??;
+                // none recalled yet or moved away: preset 0, controllers poll it from the start
+                self.send_reply(&ViscaReply::InquiryData(vec![preset.unwrap_or(0)]));
+            },
+            ViscaInquiry::PanTiltMaxSpeed() => { // Pan-tiltMaxSpeedInq
+                self.send_reply(&ViscaReply::InquiryData(vec![self.profile.max_speed.0, self.profile.max_speed.1]));
+            },
+            ViscaInquiry::Tally() => {
+                let on = self.query_status().await?.tally;
+                self.send_reply(&ViscaReply::InquiryData(vec![if on {2u8} else {3u8}]));
+            },
+            ViscaInquiry::PanTiltPos() => { // Pan-tiltPosInq
                 let (s, r) = oneshot::channel();
                 self.send_to_cam(protos::CamCmd::QueryPanTilt(s)).await?;
-                let pantilt = r.await.map_err(|_x| UVIError::AsyncChannelNoSender)?;
-                let mut v = vec![0x50u8];
+                let pantilt = r.await.map_err(|_x| UVIError::AsyncChannelNoSender)??;
+                let mut v = Vec::new();
                 v.extend(&sec_angle_to_nibbles(pantilt.pan,5));
                 v.extend(&sec_angle_to_nibbles(pantilt.tilt,4));
-                self.send_datagram(&v).await;
-            } else if dg[2] == 0x04 && dg[3] == 0x38 { // CAM_FocusModeInq
+                self.send_reply(&ViscaReply::InquiryData(v));
+            },
+            ViscaInquiry::PanTiltLimit(corner) => {
+                let (s, r) = oneshot::channel();
+                self.send_to_cam(protos::CamCmd::QueryPanTiltLimit(corner, s)).await?;
+                let limit = r.await.map_err(|_x| UVIError::AsyncChannelNoSender)??;
+                let mut v = Vec::new();
+                v.extend(&sec_angle_to_nibbles(limit.pan,5)); // like Pan-tiltPosInq
+                v.extend(&sec_angle_to_nibbles(limit.tilt,4));
+                self.send_reply(&ViscaReply::InquiryData(v));
+            },
+            ViscaInquiry::AeMode() => { // CAM_AEInq
+                let status = self.query_status().await?;
+                self.send_reply(&ViscaReply::InquiryData(vec![status.ae_mode.ok_or(UVIError::CamControlNotFound)?]));
+            },
+            ViscaInquiry::ExposurePos(e) => { // CAM_ShutterPosInq, CAM_IrisPosInq...
+                let status = self.query_status().await?;
+                let pos = match e {
+                    visca::Exposure::Iris => DEFAULT_IRIS,
+                    _ => exposure_pos(e, &status).ok_or(UVIError::CamControlNotFound)?
+                };
+                self.send_reply(&ViscaReply::InquiryData(visca::int_to_nibbles(pos as i64, 4)));
+            },
+            ViscaInquiry::ExpCompMode() => { // CAM_ExpCompModeInq
+                let status = self.query_status().await?;
+                let on = status.exp_comp_on.ok_or(UVIError::CamControlNotFound)?;
+                self.send_reply(&ViscaReply::InquiryData(vec![if on {2u8} else {3u8}]));
+            },
+            ViscaInquiry::Backlight() => { // CAM_BackLightModeInq
+                let status = self.query_status().await?;
+                let on = status.backlight.ok_or(UVIError::CamControlNotFound)?;
+                self.send_reply(&ViscaReply::InquiryData(vec![if on {2u8} else {3u8}]));
+            },
+            ViscaInquiry::LrReverse() => { // CAM_LR_ReverseInq
+                let status = self.query_status().await?;
+                let on = status.hflip.ok_or(UVIError::CamControlNotFound)?;
+                self.send_reply(&ViscaReply::InquiryData(vec![if on {2u8} else {3u8}]));
+            },
+            ViscaInquiry::PictureFlip() => { // CAM_PictureFlipInq
+                let status = self.query_status().await?;
+                let on = status.hflip.ok_or(UVIError::CamControlNotFound)? &&
+                    status.vflip.ok_or(UVIError::CamControlNotFound)?;
+                self.send_reply(&ViscaReply::InquiryData(vec![if on {2u8} else {3u8}]));
+            },
+            ViscaInquiry::Flip() => { // CAM_FlipInq
+                let status = self.query_status().await?;
+                if status.hflip.is_none() && status.vflip.is_none() { return Err(UVIError::CamControlNotFound); }
+                let f = (status.hflip.unwrap_or(false) as u8) | ((status.vflip.unwrap_or(false) as u8) << 1);
+                self.send_reply(&ViscaReply::InquiryData(vec![f]));
+            },
+            ViscaInquiry::Aperture() => { // CAM_ApertureInq
+                let sharpness = self.query_status().await?.sharpness.ok_or(UVIError::CamControlNotFound)?;
+                let pos = fraction_to_pos(sharpness, APERTURE_MAX_POS);
+                self.send_reply(&ViscaReply::InquiryData(visca::int_to_nibbles(pos as i64, 4)));
+            },
+            ViscaInquiry::ColorGain() => { // CAM_ColorGainInq
+                let saturation = self.query_status().await?.saturation.ok_or(UVIError::CamControlNotFound)?;
+                let pos = fraction_to_pos(saturation, COLOR_GAIN_MAX_POS);
+                self.send_reply(&ViscaReply::InquiryData(visca::int_to_nibbles(pos as i64, 4)));
+            },
+            ViscaInquiry::Contrast() => { // CAM_ContrastInq
+                let contrast = self.query_status().await?.contrast.ok_or(UVIError::CamControlNotFound)?;
+                let pos = fraction_to_pos(contrast, CONTRAST_MAX_POS);
+                self.send_reply(&ViscaReply::InquiryData(visca::int_to_nibbles(pos as i64, 4)));
+            },
+            ViscaInquiry::Gamma() => { // CAM_GammaInq
+                let gamma = self.query_status().await?.gamma.ok_or(UVIError::CamControlNotFound)?;
+                self.send_reply(&ViscaReply::InquiryData(vec![fraction_to_pos(gamma, GAMMA_MAX_POS)]));
+            },
+            ViscaInquiry::AfSensitivity() => { // CAM_AFSensitivityInq
+                let low = self.query_status().await?.af_low.ok_or(UVIError::CamControlNotFound)?;
+                self.send_reply(&ViscaReply::InquiryData(vec![if low {3u8} else {2u8}]));
+            },
+            ViscaInquiry::AfMode() => { // CAM_AFModeInq
+                let mode = self.query_status().await?.af_mode.ok_or(UVIError::CamControlNotFound)?;
+                self.send_reply(&ViscaReply::InquiryData(vec![mode]));
+            },
+            ViscaInquiry::AfIntervalTime() => { // CAM_AFTimeSettingInq
+                let (active, staying) = self.query_status().await?.af_interval.ok_or(UVIError::CamControlNotFound)?;
+                let mut v = visca::int_to_nibbles(active as i64, 2);
+                v.extend(visca::int_to_nibbles(staying as i64, 2));
+                self.send_reply(&ViscaReply::InquiryData(v));
+            },
+            ViscaInquiry::FocusMode() => { // CAM_FocusModeInq
                 let (s, r) = oneshot::channel();
                 self.send_to_cam(protos::CamCmd::QueryFocusMode(s)).await?;
-                let mode = r.await.map_err(|_x| UVIError::AsyncChannelNoSender)?;
-                let mut v = vec![0x50u8];
-                v.extend([if mode {2u8} else {3u8}]);
-                self.send_datagram(&v).await;
-            } else if dg[2] == 0x04 && dg[3] == 0x35 { // CAM_WhiteBalInq
+                let mode = r.await.map_err(|_x| UVIError::AsyncChannelNoSender)??;
+                self.send_reply(&ViscaReply::InquiryData(vec![if mode {2u8} else {3u8}]));
+            },
+            ViscaInquiry::RGain() => { // CAM_RGainInq
+                let red = self.query_status().await?.red_gain.ok_or(UVIError::CamControlNotFound)?;
+                self.send_reply(&ViscaReply::InquiryData(visca::int_to_nibbles(rb_gain_to_visca(Some(red)), 4)));
+            },
+            ViscaInquiry::BGain() => { // CAM_BGainInq
+                let blue = self.query_status().await?.blue_gain.ok_or(UVIError::CamControlNotFound)?;
+                self.send_reply(&ViscaReply::InquiryData(visca::int_to_nibbles(rb_gain_to_visca(Some(blue)), 4)));
+            },
+            ViscaInquiry::WhiteBalanceMode() => { // CAM_WhiteBalInq
                 let (s, r) = oneshot::channel();
                 self.send_to_cam(protos::CamCmd::QueryWhiteBalanceMode(s)).await?;
-                let mode = r.await.map_err(|_x| UVIError::AsyncChannelNoSender)?;
-                let mut v = vec![0x50u8];
-                v.extend([mode]);
-                self.send_datagram(&v).await;
-            } else if dg[2] == 0x7e && dg[3] == 0x7e { // Block Inquiry
-                //print('Block Inq '+str(dg[4]))
-                if dg[4] == 0x00 { // Lens control
-                    //y0 50 0u 0u 0u 0u 00 00 0v 0v 0v 0v 00 0w 00 FF
-                    //uuuu: Zoom Position
-                    //vvvv: Focus Position
-                    //w.bit0: Focus Mode 1: Auto 0: Manual
-                    let (s, r) = oneshot::channel();
-                    self.send_to_cam(protos::CamCmd::QueryFocusMode(s)).await?;
-                    let mode = r.await.map_err(|_x| UVIError::AsyncChannelNoSender)?;
-                    let mut v = vec![0x50u8];
-                    for _ in 0..11 { v.extend([0u8]); }
-                    v.extend([if mode {1u8} else {0u8}]);
-                    v.extend([0u8]);
-                    self.send_datagram(&v).await;
-                } else if dg[4] == 0x01 { // Camera control
-                    //y0 50 0p 0p 0q 0q 0r 0s tt 0u vv ww 00 xx 0z FF
-                    //pp: R_Gain
-                    //qq: B_Gain
-                    //r: WB Mode
-                    //s: Aperture
-                    //tt: AE Mode
-                    //u.bit2: Back Light
-                    //u.bit1: Exposure Comp.
-                    //vv: Shutter Position
-                    //ww: Iris Position
-                    //xx: Bright Position
-                    //z: Exposure Comp. Position
-                    self.send_datagram(&[0x60u8, 0x02u8]).await;
-                    //self.send_datagram([0x50] + [0]*13)
-                } else if dg[4] == 0x03 { // Other enlargement 1
-                    //y0 50 00 00 00 00 00 00 00 0p 0q rr 0s 0t 0u FF
-                    //p: AF sensitivity
-                    //q.bit0: Picture flip(1:On, 0:Off)
-                    // rr.bit6~3: Color Gain(0h(60%) to Eh(200%))
-                    //s: Flip(0: Off, 1:Flip-H, 2:Flip-V, 3:Flip-HV)
-                    //t.bit2~0: NR2D Level
-                    //u: Gain Limit
-                    self.send_datagram(&[0x60u8, 0x02u8]).await;
-                } else {
-                    self.send_datagram(&[0x60u8, 0x02u8]).await;
-                }
-            } else {
-                println!("Inquiry unknown: {}", list_to_hex(&dg));
-            }
-            self.send_datagram(&[0x60u8, 0x02u8]).await;
-        }
-        else {
-            println!("Not recognized: {}", list_to_hex(&dg));
-            self.send_datagram(&[0x60u8, 0x02u8]).await;
+                let mode = r.await.map_err(|_x| UVIError::AsyncChannelNoSender)??;
+                self.send_reply(&ViscaReply::InquiryData(vec![mode]));
+            },
+            ViscaInquiry::ZoomPos() => { // CAM_ZoomPosInq
+                let status = self.query_status().await?;
+                let zoom = status.zoom.ok_or(UVIError::CamControlNotFound)?;
+                let zoom = zoom_to_visca(zoom, status.zoom_optical, &self.profile);
+                self.send_reply(&ViscaReply::InquiryData(visca::int_to_nibbles(zoom, 4)));
+            },
+            ViscaInquiry::DigitalZoom() => { // CAM_DZoomModeInq
+                let on = self.query_status().await?.digital_zoom.ok_or(UVIError::CamControlNotFound)?;
+                self.send_reply(&ViscaReply::InquiryData(vec![if on {2u8} else {3u8}]));
+            },
+            ViscaInquiry::FocusPos() => { // CAM_FocusPosInq
+                let (s, r) = oneshot::channel();
+                self.send_to_cam(protos::CamCmd::QueryFocus(s)).await?;
+                let focus = r.await.map_err(|_x| UVIError::AsyncChannelNoSender)??;
+                self.send_reply(&ViscaReply::InquiryData(visca::int_to_nibbles(focus_to_visca(focus), 4)));
+            },
+            ViscaInquiry::Block(n @ (0x00 | 0x01 | 0x03)) => {
+                let status = self.query_status().await?;
+                let v = match n {
+                    0x00 => lens_block(&status, &self.profile),
+                    0x01 => camera_block(&status),
+                    _ => enlargement_block(&status)
+                };
+                self.send_reply(&ViscaReply::InquiryData(v));
+            },
+            ViscaInquiry::Block(_) => {
+                self.send_reply(&ViscaReply::Error(0, ViscaErrorCode::Syntax));
+            },
         }
         Ok(())
     }
@@ -281,13 +1151,14 @@ pub async fn activate_visca_port(port: u32, ncam: u8, main_chan: mpsc::Sender<pr
                 _ = recvkill.recv() => {
                     break;
                 },
+                _ = cam_chan.closed() => {
+                    break;
+                },
                 acc = listener.accept() => {
-                    let (socket, _socket_addr) = acc.expect("Bad accept?");
-                    let mut v = ViscaIpCon {
-                        ncam: ncam,
+                    let (socket, socket_addr) = acc.expect("Bad accept?");
+                    let mut v = ViscaTcpCon {
+                        con: ViscaIpCon::new(ncam, socket_addr, main_chan.clone(), cam_chan.clone()),
                         stream: socket,
-                        main_chan: main_chan.clone(),
-                        cam_chan: cam_chan.clone(),
                         recvkill: sendkill.subscribe()
                     };
                     let sendkill = sendkill.clone();
@@ -308,3 +1179,777 @@ pub async fn activate_visca_port(port: u32, ncam: u8, main_chan: mpsc::Sender<pr
     Ok(())
 }
 
+// address of the ports for the controllers of the network, only this computer
+// unless the configuration opens them (listen = 0.0.0.0)
+fn listen_address() -> &'static str {
+    config::settings().get("listen").unwrap_or("127.0.0.1")
+}
+
+// Sony VISCA over IP: every controller (peer address) gets its own ViscaIpCon
+pub async fn activate_visca_udp_port(port: u32, ncam: u8, main_chan: mpsc::Sender<protos::MainEvent>, 
+        cam_chan: mpsc::UnboundedSender<protos::CamCmd>) -> Result<(), UVIError> {
+    let socket = UdpSocket::bind(format!("{}:{}",listen_address(),port)).await?;
+    run_visca_udp_port(socket, ncam, main_chan, cam_chan);
+    Ok(())
+}
+
+fn run_visca_udp_port(socket: UdpSocket, ncam: u8, main_chan: mpsc::Sender<protos::MainEvent>, 
+        cam_chan: mpsc::UnboundedSender<protos::CamCmd>) {
+    let socket = Arc::new(socket);
+    task::spawn(async move {
+        let mut peers: HashMap<net::SocketAddr, mpsc::Sender<Vec<u8>>> = HashMap::new();
+        let mut buf = vec![0u8;1024];
+        loop {
+            select! {
+                _ = cam_chan.closed() => {
+                    break;
+                },
+                recv = socket.recv_from(&mut buf) => {
+                    let (n, peer) = match recv {
+                        Ok(x) => x,
+                        Err(_e) => continue // ICMP port unreachable from a gone peer, etc.
+                    };
+                    let dg = buf[..n].to_vec();
+                    // a stalled peer loses its datagrams, the others go on
+                    let dg = match peers.get(&peer) {
+                        Some(send_dg) => match send_dg.try_send(dg) {
+                            Ok(()) => continue,
+                            Err(mpsc::error::TrySendError::Full(_dg)) => {
+                                eprintln!("VISCA over IP: queue of {} full, datagram dropped", peer);
+                                continue;
+                            },
+                            Err(mpsc::error::TrySendError::Closed(dg)) => dg
+                        },
+                        None => dg
+                    };
+                    let (send_dg, recv_dg) = mpsc::channel(100);
+                    send_dg.try_send(dg).ok();
+                    peers.retain(|_, s| !s.is_closed());
+                    peers.insert(peer, send_dg);
+                    let mut v = ViscaUdpCon {
+                        con: ViscaIpCon::new(ncam, peer, main_chan.clone(), cam_chan.clone()),
+                        socket: socket.clone(),
+                        recv_dg: recv_dg
+                    };
+                    task::spawn(async move {
+                        match v.process().await {
+                            Err(e) => {
+                                eprintln!("Closing ViscaIP UDP peer for error: {}", e);
+                            }
+                            _ => ()
+                        }
+                    });
+                }
+            }
+        }
+    });
+}
+
+// serial-era daisy chain: every camera of the chain on one port, at the VISCA
+// addresses given by Address Set (1, 2, 3... until then)
+pub async fn activate_visca_shared_port(port: u32, chain: ViscaChain,
+        main_chan: mpsc::Sender<protos::MainEvent>) -> Result<(), UVIError> {
+    let listener = TcpListener::bind(format!("{}:{}",listen_address(),port)).await?;
+    task::spawn(async move {
+        loop {
+            let (socket, socket_addr) = match listener.accept().await {
+                Ok(acc) => acc,
+                Err(_e) => continue
+            };
+            let (send_out, recv_out) = mpsc::channel(100);
+            let mut v = ViscaChainLink {
+                chain: chain.clone(),
+                peer: socket_addr,
+                main_chan: main_chan.clone(),
+                stream: socket,
+                first: 1,
+                cons: HashMap::new(),
+                send_out: send_out,
+                recv_out: recv_out
+            };
+            task::spawn(async move {
+                match v.process().await {
+                    Err(e) => {
+                        eprintln!("Closing VISCA shared port connection for error: {}", e);
+                    }
+                    _ => ()
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU8, Ordering};
+
+    // message, replies in order, commands sent to the camera in order
+    struct Case {
+        pkt: &'static [u8],
+        replies: &'static [&'static [u8]],
+        cmds: &'static [&'static str],
+    }
+
+    // variant and values of a camera command, without the channels of the answer
+    fn describe(cmd: &protos::CamCmd) -> String {
+        let s = format!("{:?}", cmd);
+        let end = ["Completion {", "Sender {"].iter().filter_map(|m| s.find(m)).min().unwrap_or(s.len());
+        format!("{})", s[..end].trim_end_matches([' ', ',']))
+    }
+
+    // the mock camera, every setting at its default whatever config.txt of the user says
+    async fn mock_camera() -> mpsc::UnboundedSender<protos::CamCmd> {
+        config::use_settings("");
+        let cam = crate::uvc::find_mock_camera(0).await.unwrap();
+        crate::auto_uvc::AutoCamera::start(cam).await.unwrap().0
+    }
+
+    // each connection with its own presets and limits on the database of the tests
+    static PRESET_NCAM: AtomicU8 = AtomicU8::new(0);
+
+    // a connection to a fresh mock camera, with the commands it receives logged
+    async fn connect() -> (ViscaIpCon, Arc<Mutex<Vec<String>>>) {
+        let cam_chan = mock_camera().await;
+        crate::presetdb::prepare_preset_db().await.unwrap();
+        cam_chan.send(protos::CamCmd::SetPresetNcam(PRESET_NCAM.fetch_add(1, Ordering::Relaxed))).unwrap();
+        let (send_cmd, mut recv_cmd) = mpsc::unbounded_channel();
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let cmds = log.clone();
+        task::spawn(async move {
+            while let Some(cmd) = recv_cmd.recv().await {
+                cmds.lock().unwrap().push(describe(&cmd));
+                if cam_chan.send(cmd).is_err() { break; }
+            }
+        });
+        let (main_chan, mut main_events) = mpsc::channel(100);
+        task::spawn(async move { while main_events.recv().await.is_some() {} });
+        (ViscaIpCon::new(0, "127.0.0.1:52381".parse().unwrap(), main_chan, send_cmd), log)
+    }
+
+    // the replies until the commands running on the sockets finish
+    async fn exchange(con: &mut ViscaIpCon, pkt: &[u8]) -> Vec<Vec<u8>> {
+        con.data_received(pkt).await.unwrap();
+        while con.sockets.iter().any(|s| s.is_some()) {
+            let (socket, id, r) = tokio::time::timeout(Duration::from_secs(10), con.recv_done.recv())
+                .await.expect("command never finished").unwrap();
+            con.done_received(socket, id, r);
+        }
+        con.outbox.drain(..).map(|(_seq, reply)| reply).collect()
+    }
+
+    async fn check(con: &mut ViscaIpCon, log: &Mutex<Vec<String>>, case: &Case) {
+        let replies = exchange(con, case.pkt).await;
+        let cmds: Vec<String> = log.lock().unwrap().drain(..).collect();
+        assert_eq!(replies, case.replies, "replies to {:02X?}", case.pkt);
+        assert_eq!(cmds, case.cmds, "camera commands of {:02X?}", case.pkt);
+    }
+
+    // each case on a camera just found
+    async fn check_all(cases: &[Case]) {
+        for case in cases {
+            let (mut con, log) = connect().await;
+            check(&mut con, &log, case).await;
+        }
+    }
+
+    // the camera commands
+    const COMMANDS: &[Case] = &[
+        // IF_Clear
+        Case { pkt: &[0x81,0x01,0x00,0x01,0xFF],
+            replies: &[&[0x90,0x50,0xFF]],
+            cmds: &[] },
+        // Home
+        Case { pkt: &[0x81,0x01,0x06,0x04,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["Home()"] },
+        // Reset
+        Case { pkt: &[0x81,0x01,0x06,0x05,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["PanTiltReset()"] },
+        // Pan-tiltDrive UpLeft
+        Case { pkt: &[0x81,0x01,0x06,0x01,0x0C,0x0A,0x01,0x01,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["MoveContinuous(PanTilt { pan: -86400, tilt: 36000 })"] },
+        // DownRight
+        Case { pkt: &[0x81,0x01,0x06,0x01,0x0C,0x0A,0x02,0x02,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["MoveContinuous(PanTilt { pan: 86400, tilt: -36000 })"] },
+        // Stop
+        Case { pkt: &[0x81,0x01,0x06,0x01,0x0C,0x0A,0x03,0x03,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["MoveContinuous(PanTilt { pan: 0, tilt: 0 })"] },
+        // AbsolutePosition
+        Case { pkt: &[0x81,0x01,0x06,0x02,0x18,0x14,0x00,0x01,0x00,0x00,0x0F,0x0F,0x00,0x00,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["MoveAbsolute(PanTilt { pan: 3907, tilt: -3907 }, PanTilt { pan: 345600, tilt: 72000 })"] },
+        // RelativePosition
+        Case { pkt: &[0x81,0x01,0x06,0x03,0x01,0x01,0x0F,0x0F,0x0F,0x00,0x00,0x00,0x01,0x00,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["MoveRelative(PanTilt { pan: -244, tilt: 244 }, PanTilt { pan: 3600, tilt: 3600 })"] },
+        // Pan-tiltLimitSet UpRight
+        Case { pkt: &[0x81,0x01,0x06,0x07,0x00,0x01,0x00,0x01,0x00,0x00,0x00,0x00,0x08,0x00,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["PanTiltLimitSet(1, PanTilt { pan: 3907, tilt: 1953 })"] },
+        // Pan-tiltLimitSet DownLeft
+        Case { pkt: &[0x81,0x01,0x06,0x07,0x00,0x00,0x0F,0x0F,0x00,0x00,0x0F,0x0F,0x08,0x00,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["PanTiltLimitSet(0, PanTilt { pan: -3907, tilt: -1953 })"] },
+        // Pan-tiltLimitClear UpRight
+        Case { pkt: &[0x81,0x01,0x06,0x07,0x01,0x01,0x07,0x0F,0x0F,0x0F,0x07,0x0F,0x0F,0x0F,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["PanTiltLimitClear(1)"] },
+        // CAM_Memory Reset
+        Case { pkt: &[0x81,0x01,0x04,0x3F,0x00,0x01,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["ResetPreset(1)"] },
+        // CAM_Memory Set
+        Case { pkt: &[0x81,0x01,0x04,0x3F,0x01,0x01,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["RecordPreset(1, None)"] },
+        // CAM_Memory Recall, nothing recorded
+        Case { pkt: &[0x81,0x01,0x04,0x3F,0x02,0x01,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["RecoverPreset(1)"] },
+        // CAM_Zoom Tele
+        Case { pkt: &[0x81,0x01,0x04,0x07,0x02,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["ZoomContinuous(1.0)"] },
+        // Wide (variable)
+        Case { pkt: &[0x81,0x01,0x04,0x07,0x35,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["ZoomContinuous(-0.75)"] },
+        // Stop
+        Case { pkt: &[0x81,0x01,0x04,0x07,0x00,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["ZoomContinuous(0.0)"] },
+        // CAM_Zoom Direct
+        Case { pkt: &[0x81,0x01,0x04,0x47,0x02,0x00,0x00,0x00,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["QueryStatus()", "ZoomDirect(0.5)"] },
+        // CAM_Zoom Focus Direct
+        Case { pkt: &[0x81,0x01,0x04,0x47,0x04,0x00,0x00,0x00,0x07,0x08,0x00,0x00,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["QueryStatus()", "ZoomFocusDirect(1.0, 0.5)"] },
+        // CAM_DZoom On
+        Case { pkt: &[0x81,0x01,0x04,0x06,0x02,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x61,0x41,0xFF]],
+            cmds: &["DigitalZoom(true)"] },
+        // CAM_Focus Auto
+        Case { pkt: &[0x81,0x01,0x04,0x38,0x02,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["AutoFocus(true)"] },
+        // Manual
+        Case { pkt: &[0x81,0x01,0x04,0x38,0x03,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["AutoFocus(false)"] },
+        // Auto/Manual
+        Case { pkt: &[0x81,0x01,0x04,0x38,0x10,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["AutoFocusToggle()"] },
+        // Far
+        Case { pkt: &[0x81,0x01,0x04,0x08,0x02,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["FocusContinuous(1.0)"] },
+        // Near (variable)
+        Case { pkt: &[0x81,0x01,0x04,0x08,0x33,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["FocusContinuous(-0.5)"] },
+        // Stop
+        Case { pkt: &[0x81,0x01,0x04,0x08,0x00,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["FocusContinuous(0.0)"] },
+        // CAM_Focus Direct
+        Case { pkt: &[0x81,0x01,0x04,0x48,0x0F,0x00,0x00,0x00,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["FocusDirect(1.0)"] },
+        // CAM_Focus Infinity
+        Case { pkt: &[0x81,0x01,0x04,0x18,0x02,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["FocusDirect(0.0)"] },
+        // CAM_AFSensitivity Low
+        Case { pkt: &[0x81,0x01,0x04,0x58,0x03,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["AfSensitivity(true)"] },
+        // CAM_AFMode Zoom Trigger
+        Case { pkt: &[0x81,0x01,0x04,0x57,0x02,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["AfMode(2)"] },
+        // CAM_AFMode Interval Time
+        Case { pkt: &[0x81,0x01,0x04,0x27,0x00,0x05,0x01,0x00,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["AfInterval(5, 16)"] },
+        // CAM_WB Auto
+        Case { pkt: &[0x81,0x01,0x04,0x35,0x00,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["WhiteBalanceMode(0)"] },
+        // Manual
+        Case { pkt: &[0x81,0x01,0x04,0x35,0x03,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["WhiteBalanceMode(3)"] },
+        // CAM_RGain Up
+        Case { pkt: &[0x81,0x01,0x04,0x03,0x02,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["QueryStatus()", "WhiteBalanceGain(Some(0.00392156862745098), None)"] },
+        // CAM_RGain Direct
+        Case { pkt: &[0x81,0x01,0x04,0x43,0x00,0x00,0x08,0x00,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["WhiteBalanceGain(Some(0.5019607843137255), None)"] },
+        // CAM_BGain Down
+        Case { pkt: &[0x81,0x01,0x04,0x04,0x03,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["QueryStatus()", "WhiteBalanceGain(None, Some(0.996078431372549))"] },
+        // CAM_BGain Direct
+        Case { pkt: &[0x81,0x01,0x04,0x44,0x00,0x00,0x04,0x00,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["WhiteBalanceGain(None, Some(0.25098039215686274))"] },
+        // CAM_AE Manual
+        Case { pkt: &[0x81,0x01,0x04,0x39,0x03,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["AutoExposure(3)"] },
+        // CAM_Shutter Up
+        Case { pkt: &[0x81,0x01,0x04,0x0A,0x02,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["QueryStatus()", "Shutter(Some(0.5))"] },
+        // CAM_Shutter Direct
+        Case { pkt: &[0x81,0x01,0x04,0x4A,0x00,0x00,0x00,0x06,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["Shutter(Some(0.016666666666666666))"] },
+        // CAM_Iris Up
+        Case { pkt: &[0x81,0x01,0x04,0x0B,0x02,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x61,0x41,0xFF]],
+            cmds: &["QueryStatus()"] },
+        // CAM_Gain Direct
+        Case { pkt: &[0x81,0x01,0x04,0x4C,0x00,0x00,0x00,0x05,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["Gain(Some(0.3333333333333333))"] },
+        // CAM_Bright Reset
+        Case { pkt: &[0x81,0x01,0x04,0x0D,0x00,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["Brightness(None)"] },
+        // CAM_ExpComp On
+        Case { pkt: &[0x81,0x01,0x04,0x3E,0x02,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["ExposureCompensationOn(true)"] },
+        // CAM_ExpComp Direct
+        Case { pkt: &[0x81,0x01,0x04,0x4E,0x00,0x00,0x00,0x0A,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["ExposureCompensation(0.4285714285714286)"] },
+        // CAM_BackLight On
+        Case { pkt: &[0x81,0x01,0x04,0x33,0x02,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["Backlight(true)"] },
+        // CAM_LR_Reverse On
+        Case { pkt: &[0x81,0x01,0x04,0x61,0x02,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["Flip(Some(true), None)"] },
+        // CAM_PictureFlip On
+        Case { pkt: &[0x81,0x01,0x04,0x66,0x02,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["Flip(Some(true), Some(true))"] },
+        // CAM_Flip H
+        Case { pkt: &[0x81,0x01,0x04,0xA4,0x01,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["Flip(Some(true), Some(false))"] },
+        // CAM_Aperture Direct
+        Case { pkt: &[0x81,0x01,0x04,0x42,0x00,0x00,0x00,0x0A,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["Sharpness(Some(0.6666666666666666))"] },
+        // CAM_ColorGain Direct
+        Case { pkt: &[0x81,0x01,0x04,0x49,0x00,0x00,0x00,0x07,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["Saturation(0.5)"] },
+        // CAM_Contrast Direct
+        Case { pkt: &[0x81,0x01,0x04,0xA2,0x00,0x00,0x08,0x00,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["Contrast(0.5019607843137255)"] },
+        // CAM_Gamma
+        Case { pkt: &[0x81,0x01,0x04,0x5B,0x03,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["Gamma(0.75)"] },
+        // preset recall speed
+        Case { pkt: &[0x81,0x01,0x7E,0x01,0x0B,0x10,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["PresetSpeed(PanTilt { pan: 115200, tilt: 57600 })"] },
+        // Tally On
+        Case { pkt: &[0x81,0x01,0x7E,0x01,0x0A,0x00,0x02,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["Tally(true)"] },
+        // Cancel, nothing running
+        Case { pkt: &[0x81,0x21,0xFF],
+            replies: &[&[0x90,0x61,0x05,0xFF]],
+            cmds: &[] },
+    ];
+
+    // the answers of the mock camera as found: every control at 0, no iris and no digital zoom
+    const INQUIRIES: &[Case] = &[
+        // CAM_VersionInq
+        Case { pkt: &[0x81,0x09,0x00,0x02,0xFF],
+            replies: &[&[0x90,0x50,0x09,0x99,0x00,0x01,0x00,0x01,0x02,0xFF]],
+            cmds: &[] },
+        // Pan-tiltMaxSpeedInq
+        Case { pkt: &[0x81,0x09,0x06,0x11,0xFF],
+            replies: &[&[0x90,0x50,0x18,0x14,0xFF]],
+            cmds: &[] },
+        // Pan-tiltPosInq
+        Case { pkt: &[0x81,0x09,0x06,0x12,0xFF],
+            replies: &[&[0x90,0x50,0x00,0x00,0x00,0x00,0x00,0x00,0x00,0x00,0x00,0xFF]],
+            cmds: &["QueryPanTilt()"] },
+        // Pan-tiltLimitInq UpRight
+        Case { pkt: &[0x81,0x09,0x06,0x07,0x01,0xFF],
+            replies: &[&[0x90,0x50,0x00,0x09,0x0C,0x0A,0x07,0x01,0x0B,0x0A,0x05,0xFF]],
+            cmds: &["QueryPanTiltLimit(1)"] },
+        // CAM_FocusModeInq
+        Case { pkt: &[0x81,0x09,0x04,0x38,0xFF],
+            replies: &[&[0x90,0x50,0x03,0xFF]],
+            cmds: &["QueryFocusMode()"] },
+        // CAM_MemoryInq, no preset yet
+        Case { pkt: &[0x81,0x09,0x04,0x3F,0xFF],
+            replies: &[&[0x90,0x50,0x00,0xFF]],
+            cmds: &["QueryPreset()"] },
+        // CAM_AFSensitivityInq
+        Case { pkt: &[0x81,0x09,0x04,0x58,0xFF],
+            replies: &[&[0x90,0x50,0x02,0xFF]],
+            cmds: &["QueryStatus()"] },
+        // CAM_AFModeInq
+        Case { pkt: &[0x81,0x09,0x04,0x57,0xFF],
+            replies: &[&[0x90,0x50,0x00,0xFF]],
+            cmds: &["QueryStatus()"] },
+        // CAM_AFTimeSettingInq
+        Case { pkt: &[0x81,0x09,0x04,0x27,0xFF],
+            replies: &[&[0x90,0x50,0x00,0x02,0x00,0x05,0xFF]],
+            cmds: &["QueryStatus()"] },
+        // CAM_WBModeInq
+        Case { pkt: &[0x81,0x09,0x04,0x35,0xFF],
+            replies: &[&[0x90,0x50,0x05,0xFF]],
+            cmds: &["QueryWhiteBalanceMode()"] },
+        // CAM_RGainInq
+        Case { pkt: &[0x81,0x09,0x04,0x43,0xFF],
+            replies: &[&[0x90,0x50,0x00,0x00,0x00,0x00,0xFF]],
+            cmds: &["QueryStatus()"] },
+        // CAM_BGainInq
+        Case { pkt: &[0x81,0x09,0x04,0x44,0xFF],
+            replies: &[&[0x90,0x50,0x00,0x00,0x0F,0x0F,0xFF]],
+            cmds: &["QueryStatus()"] },
+        // CAM_ZoomPosInq
+        Case { pkt: &[0x81,0x09,0x04,0x47,0xFF],
+            replies: &[&[0x90,0x50,0x00,0x00,0x00,0x00,0xFF]],
+            cmds: &["QueryStatus()"] },
+        // CAM_DZoomModeInq
+        Case { pkt: &[0x81,0x09,0x04,0x06,0xFF],
+            replies: &[&[0x90,0x60,0x41,0xFF]],
+            cmds: &["QueryStatus()"] },
+        // CAM_FocusPosInq
+        Case { pkt: &[0x81,0x09,0x04,0x48,0xFF],
+            replies: &[&[0x90,0x50,0x00,0x00,0x00,0x00,0xFF]],
+            cmds: &["QueryFocus()"] },
+        // CAM_AEModeInq
+        Case { pkt: &[0x81,0x09,0x04,0x39,0xFF],
+            replies: &[&[0x90,0x50,0x00,0xFF]],
+            cmds: &["QueryStatus()"] },
+        // CAM_ShutterPosInq
+        Case { pkt: &[0x81,0x09,0x04,0x4A,0xFF],
+            replies: &[&[0x90,0x50,0x00,0x00,0x00,0x00,0xFF]],
+            cmds: &["QueryStatus()"] },
+        // CAM_IrisPosInq
+        Case { pkt: &[0x81,0x09,0x04,0x4B,0xFF],
+            replies: &[&[0x90,0x50,0x00,0x00,0x00,0x08,0xFF]],
+            cmds: &["QueryStatus()"] },
+        // CAM_GainPosInq
+        Case { pkt: &[0x81,0x09,0x04,0x4C,0xFF],
+            replies: &[&[0x90,0x50,0x00,0x00,0x00,0x00,0xFF]],
+            cmds: &["QueryStatus()"] },
+        // CAM_BrightPosInq
+        Case { pkt: &[0x81,0x09,0x04,0x4D,0xFF],
+            replies: &[&[0x90,0x50,0x00,0x00,0x00,0x00,0xFF]],
+            cmds: &["QueryStatus()"] },
+        // CAM_ExpCompPosInq
+        Case { pkt: &[0x81,0x09,0x04,0x4E,0xFF],
+            replies: &[&[0x90,0x50,0x00,0x00,0x00,0x07,0xFF]],
+            cmds: &["QueryStatus()"] },
+        // CAM_ExpCompModeInq
+        Case { pkt: &[0x81,0x09,0x04,0x3E,0xFF],
+            replies: &[&[0x90,0x50,0x03,0xFF]],
+            cmds: &["QueryStatus()"] },
+        // CAM_BackLightModeInq
+        Case { pkt: &[0x81,0x09,0x04,0x33,0xFF],
+            replies: &[&[0x90,0x50,0x03,0xFF]],
+            cmds: &["QueryStatus()"] },
+        // CAM_LR_ReverseInq
+        Case { pkt: &[0x81,0x09,0x04,0x61,0xFF],
+            replies: &[&[0x90,0x50,0x03,0xFF]],
+            cmds: &["QueryStatus()"] },
+        // CAM_PictureFlipInq
+        Case { pkt: &[0x81,0x09,0x04,0x66,0xFF],
+            replies: &[&[0x90,0x50,0x03,0xFF]],
+            cmds: &["QueryStatus()"] },
+        // CAM_FlipInq
+        Case { pkt: &[0x81,0x09,0x04,0xA4,0xFF],
+            replies: &[&[0x90,0x50,0x00,0xFF]],
+            cmds: &["QueryStatus()"] },
+        // CAM_ApertureInq
+        Case { pkt: &[0x81,0x09,0x04,0x42,0xFF],
+            replies: &[&[0x90,0x50,0x00,0x00,0x00,0x00,0xFF]],
+            cmds: &["QueryStatus()"] },
+        // CAM_ColorGainInq
+        Case { pkt: &[0x81,0x09,0x04,0x49,0xFF],
+            replies: &[&[0x90,0x50,0x00,0x00,0x00,0x00,0xFF]],
+            cmds: &["QueryStatus()"] },
+        // CAM_ContrastInq
+        Case { pkt: &[0x81,0x09,0x04,0xA2,0xFF],
+            replies: &[&[0x90,0x50,0x00,0x00,0x00,0x00,0xFF]],
+            cmds: &["QueryStatus()"] },
+        // CAM_GammaInq
+        Case { pkt: &[0x81,0x09,0x04,0x5B,0xFF],
+            replies: &[&[0x90,0x50,0x00,0xFF]],
+            cmds: &["QueryStatus()"] },
+        // tally
+        Case { pkt: &[0x81,0x09,0x7E,0x01,0x0A,0xFF],
+            replies: &[&[0x90,0x50,0x03,0xFF]],
+            cmds: &["QueryStatus()"] },
+        // CAM_LensBlockInq
+        Case { pkt: &[0x81,0x09,0x7E,0x7E,0x00,0xFF],
+            replies: &[&[0x90,0x50,0x00,0x00,0x00,0x00,0x00,0x00,0x00,0x00,0x00,0x00,0x00,0x00,0x00,0xFF]],
+            cmds: &["QueryStatus()"] },
+        // CAM_CameraBlockInq
+        Case { pkt: &[0x81,0x09,0x7E,0x7E,0x01,0xFF],
+            replies: &[&[0x90,0x50,0x00,0x00,0x0F,0x0F,0x05,0x00,0x00,0x00,0x00,0x08,0x00,0x00,0x07,0xFF]],
+            cmds: &["QueryStatus()"] },
+        // enlargement block
+        Case { pkt: &[0x81,0x09,0x7E,0x7E,0x03,0xFF],
+            replies: &[&[0x90,0x50,0x00,0x00,0x00,0x00,0x00,0x00,0x00,0x02,0x00,0x00,0x00,0x00,0x0F,0xFF]],
+            cmds: &["QueryStatus()"] },
+    ];
+
+    const ERRORS: &[Case] = &[
+        // block inquiry not answered
+        Case { pkt: &[0x81,0x09,0x7E,0x7E,0x02,0xFF],
+            replies: &[&[0x90,0x60,0x02,0xFF]],
+            cmds: &[] },
+        // unknown command
+        Case { pkt: &[0x81,0x01,0x09,0x09,0xFF],
+            replies: &[&[0x90,0x60,0x02,0xFF]],
+            cmds: &[] },
+        // truncated message
+        Case { pkt: &[0x81,0x01,0x06,0x01,0x0C,0xFF],
+            replies: &[&[0x90,0x60,0x02,0xFF]],
+            cmds: &[] },
+        // another camera address, answered from it
+        Case { pkt: &[0x83,0x09,0x00,0x02,0xFF],
+            replies: &[&[0xB0,0x50,0x09,0x99,0x00,0x01,0x00,0x01,0x02,0xFF]],
+            cmds: &[] },
+        // not from a controller, ignored
+        Case { pkt: &[0x91,0x09,0x00,0x02,0xFF],
+            replies: &[],
+            cmds: &[] },
+        // no terminator
+        Case { pkt: &[0x81,0x09,0x00,0x02],
+            replies: &[&[0x90,0x60,0x02,0xFF]],
+            cmds: &[] },
+    ];
+
+    #[tokio::test]
+    async fn commands() {
+        check_all(COMMANDS).await;
+    }
+
+    #[tokio::test]
+    async fn inquiries() {
+        check_all(INQUIRIES).await;
+    }
+
+    #[tokio::test]
+    async fn errors() {
+        check_all(ERRORS).await;
+    }
+
+    // the slowest speed of a controller still moves, whatever the maximum of the profile
+    #[test]
+    fn speeds() {
+        assert_eq!(scale_speed(0, 0x18, 0x14), 0);
+        assert_eq!(scale_speed(1, 0x18, 0x14), 1);
+        assert_eq!(scale_speed(0x18, 0x18, 0x14), 0x14);
+        assert_eq!(scale_speed(0x1F, 0x18, 0x14), 0x14);
+        assert_eq!(scale_speed(1, 0x14, 0x14), 1);
+    }
+
+    // positions are read back as they were set, negative ones included
+    #[tokio::test]
+    async fn position_round_trip() {
+        let (mut con, log) = connect().await;
+        for case in &[
+            Case { pkt: &[0x81,0x01,0x06,0x02,0x18,0x14,0x0F,0x0F,0x00,0x00,0x00,0x00,0x08,0x00,0xFF],
+                replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+                cmds: &["MoveAbsolute(PanTilt { pan: -3907, tilt: 1953 }, PanTilt { pan: 345600, tilt: 72000 })"] },
+            Case { pkt: &[0x81,0x09,0x06,0x12,0xFF],
+                replies: &[&[0x90,0x50,0x0F,0x0F,0x0F,0x00,0x00,0x00,0x00,0x08,0x00,0xFF]],
+                cmds: &["QueryPanTilt()"] },
+            Case { pkt: &[0x81,0x01,0x06,0x03,0x18,0x14,0x00,0x00,0x01,0x00,0x0F,0x0F,0x0F,0x00,0xFF],
+                replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+                cmds: &["MoveRelative(PanTilt { pan: 244, tilt: -244 }, PanTilt { pan: 345600, tilt: 72000 })"] },
+            Case { pkt: &[0x81,0x09,0x06,0x12,0xFF],
+                replies: &[&[0x90,0x50,0x0F,0x0F,0x0F,0x01,0x00,0x00,0x00,0x07,0x00,0xFF]],
+                cmds: &["QueryPanTilt()"] },
+        ] {
+            check(&mut con, &log, case).await;
+        }
+    }
+
+    // a recalled preset brings the camera back where it was recorded
+    #[tokio::test]
+    async fn preset_recall() {
+        let (mut con, log) = connect().await;
+        for case in &[
+            Case { pkt: &[0x81,0x01,0x06,0x02,0x18,0x14,0x0F,0x0F,0x00,0x00,0x00,0x00,0x08,0x00,0xFF],
+                replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+                cmds: &["MoveAbsolute(PanTilt { pan: -3907, tilt: 1953 }, PanTilt { pan: 345600, tilt: 72000 })"] },
+            Case { pkt: &[0x81,0x01,0x04,0x3F,0x01,0x02,0xFF],
+                replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+                cmds: &["RecordPreset(2, None)"] },
+            Case { pkt: &[0x81,0x01,0x06,0x04,0xFF],
+                replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+                cmds: &["Home()"] },
+            Case { pkt: &[0x81,0x09,0x06,0x12,0xFF],
+                replies: &[&[0x90,0x50,0x00,0x00,0x00,0x00,0x00,0x00,0x00,0x00,0x00,0xFF]],
+                cmds: &["QueryPanTilt()"] },
+            Case { pkt: &[0x81,0x01,0x04,0x3F,0x02,0x02,0xFF],
+                replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+                cmds: &["RecoverPreset(2)"] },
+            Case { pkt: &[0x81,0x09,0x06,0x12,0xFF],
+                replies: &[&[0x90,0x50,0x0F,0x0F,0x0F,0x00,0x00,0x00,0x00,0x08,0x00,0xFF]],
+                cmds: &["QueryPanTilt()"] },
+            Case { pkt: &[0x81,0x09,0x04,0x3F,0xFF],
+                replies: &[&[0x90,0x50,0x02,0xFF]],
+                cmds: &["QueryPreset()"] },
+        ] {
+            check(&mut con, &log, case).await;
+        }
+    }
+
+    // a preset keeps the speed set before its recording, over the later speed of the camera
+    #[tokio::test]
+    async fn preset_recall_speed() {
+        let (mut con, log) = connect().await;
+        for case in &[
+            Case { pkt: &[0x81,0x01,0x7E,0x01,0x0B,0x08,0xFF],
+                replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+                cmds: &["PresetSpeed(PanTilt { pan: 28800, tilt: 28800 })"] },
+            Case { pkt: &[0x81,0x01,0x06,0x02,0x18,0x14,0x00,0x04,0x00,0x00,0x00,0x00,0x00,0x00,0xFF],
+                replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+                cmds: &["MoveAbsolute(PanTilt { pan: 15627, tilt: 0 }, PanTilt { pan: 345600, tilt: 72000 })"] },
+            Case { pkt: &[0x81,0x01,0x04,0x3F,0x01,0x03,0xFF],
+                replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+                cmds: &["RecordPreset(3, Some(PanTilt { pan: 28800, tilt: 28800 }))"] },
+            Case { pkt: &[0x81,0x01,0x7E,0x01,0x0B,0x18,0xFF],
+                replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+                cmds: &["PresetSpeed(PanTilt { pan: 345600, tilt: 72000 })"] },
+            Case { pkt: &[0x81,0x01,0x06,0x04,0xFF],
+                replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+                cmds: &["Home()"] },
+        ] {
+            check(&mut con, &log, case).await;
+        }
+        // ~0.5s for 4.3 degrees at 8 degrees per second, 0.05s at the speed of the camera
+        let start = Instant::now();
+        check(&mut con, &log, &Case { pkt: &[0x81,0x01,0x04,0x3F,0x02,0x03,0xFF],
+            replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+            cmds: &["RecoverPreset(3)"] }).await;
+        assert!(start.elapsed() >= Duration::from_millis(400), "recalled in {:?}", start.elapsed());
+        check(&mut con, &log, &Case { pkt: &[0x81,0x09,0x06,0x12,0xFF],
+            replies: &[&[0x90,0x50,0x00,0x00,0x04,0x00,0x00,0x00,0x00,0x00,0x00,0xFF]],
+            cmds: &["QueryPanTilt()"] }).await;
+    }
+
+    // the sensitivity set is the one of the enlargement block
+    #[tokio::test]
+    async fn af_sensitivity_block() {
+        let (mut con, log) = connect().await;
+        for case in &[
+            Case { pkt: &[0x81,0x01,0x04,0x58,0x03,0xFF],
+                replies: &[&[0x90,0x41,0xFF], &[0x90,0x51,0xFF]],
+                cmds: &["AfSensitivity(true)"] },
+            Case { pkt: &[0x81,0x09,0x7E,0x7E,0x03,0xFF],
+                replies: &[&[0x90,0x50,0x00,0x00,0x00,0x00,0x00,0x00,0x00,0x03,0x00,0x00,0x00,0x00,0x0F,0xFF]],
+                cmds: &["QueryStatus()"] },
+        ] {
+            check(&mut con, &log, case).await;
+        }
+    }
+
+    #[test]
+    fn nibbles() {
+        assert_eq!(visca::int_to_nibbles(0x1234, 4), [0x1,0x2,0x3,0x4]);
+        assert_eq!(visca::int_to_nibbles(-1, 4), [0xF,0xF,0xF,0xF]);
+        assert_eq!(visca::int_to_nibbles(-0x100, 5), [0xF,0xF,0xF,0x0,0x0]);
+        assert_eq!(visca::nibbles_to_int(&[0x7,0xF,0xF,0xF]), 0x7FFF);
+        assert_eq!(visca::nibbles_to_int(&[0x8,0x0,0x0,0x0]), -0x8000);
+        assert_eq!(visca::nibbles_to_uint(&[0x8,0x0,0x0,0x0]), 0x8000);
+        for v in (-0x8000..0x8000).step_by(7) {
+            assert_eq!(visca::nibbles_to_int(&sec_angle_to_nibbles(visca_to_sec_angle(v), 5)), v);
+        }
+    }
+
+    // a controller on the VISCA over IP port of a fresh mock camera
+    async fn udp_controller() -> UdpSocket {
+        let cam_chan = mock_camera().await;
+        let (main_chan, mut main_events) = mpsc::channel(100);
+        task::spawn(async move { while main_events.recv().await.is_some() {} });
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let address = socket.local_addr().unwrap();
+        run_visca_udp_port(socket, 0, main_chan, cam_chan);
+        let controller = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        controller.connect(address).await.unwrap();
+        controller
+    }
+
+    fn voip(typ: u16, seq: u32, payload: &[u8]) -> Vec<u8> {
+        [&typ.to_be_bytes()[..], &(payload.len() as u16).to_be_bytes(), &seq.to_be_bytes(), payload].concat()
+    }
+
+    async fn voip_reply(controller: &UdpSocket) -> Vec<u8> {
+        let mut buf = vec![0u8; 1024];
+        let n = tokio::time::timeout(Duration::from_secs(10), controller.recv(&mut buf))
+            .await.expect("no reply").unwrap();
+        buf[..n].to_vec()
+    }
+
+    // replies carry the sequence number of their message, several messages may share a datagram
+    #[tokio::test]
+    async fn udp_framing() {
+        let c = udp_controller().await;
+        c.send(&voip(VOIP_VISCA_COMMAND, 7, &[0x81,0x01,0x06,0x04,0xFF])).await.unwrap();
+        assert_eq!(voip_reply(&c).await, voip(VOIP_VISCA_REPLY, 7, &[0x90,0x41,0xFF]));
+        assert_eq!(voip_reply(&c).await, voip(VOIP_VISCA_REPLY, 7, &[0x90,0x51,0xFF]));
+        c.send(&voip(VOIP_VISCA_INQUIRY, 8, &[0x81,0x09,0x06,0x11,0xFF,0x81,0x09,0x7E,0x01,0x0A,0xFF])).await.unwrap();
+        assert_eq!(voip_reply(&c).await, voip(VOIP_VISCA_REPLY, 8, &[0x90,0x50,0x18,0x14,0xFF]));
+        assert_eq!(voip_reply(&c).await, voip(VOIP_VISCA_REPLY, 8, &[0x90,0x50,0x03,0xFF]));
+        c.send(&voip(VOIP_VISCA_COMMAND, 9, &[0x81,0x01,0x09,0x09,0xFF])).await.unwrap();
+        assert_eq!(voip_reply(&c).await, voip(VOIP_VISCA_REPLY, 9, &[0x90,0x60,0x02,0xFF]));
+    }
+
+    #[tokio::test]
+    async fn udp_control() {
+        let c = udp_controller().await;
+        // RESET, then the sequence starts again at 0
+        c.send(&voip(VOIP_CONTROL_COMMAND, 41, &[0x01])).await.unwrap();
+        assert_eq!(voip_reply(&c).await, voip(VOIP_CONTROL_REPLY, 41, &[0x01]));
+        c.send(&voip(VOIP_VISCA_INQUIRY, 0, &[0x81,0x09,0x06,0x11,0xFF])).await.unwrap();
+        assert_eq!(voip_reply(&c).await, voip(VOIP_VISCA_REPLY, 0, &[0x90,0x50,0x18,0x14,0xFF]));
+        // length of the header not matching the payload
+        let mut dg = voip(VOIP_VISCA_COMMAND, 1, &[0x81,0x01,0x06,0x04,0xFF]);
+        dg[3] = 0x09;
+        c.send(&dg).await.unwrap();
+        assert_eq!(voip_reply(&c).await, voip(VOIP_CONTROL_REPLY, 1, &[0x0F,0x02]));
+        // unknown control command and payload type
+        c.send(&voip(VOIP_CONTROL_COMMAND, 2, &[0x05])).await.unwrap();
+        assert_eq!(voip_reply(&c).await, voip(VOIP_CONTROL_REPLY, 2, &[0x0F,0x02]));
+        c.send(&voip(0x0300, 3, &[0x81,0x09,0x06,0x11,0xFF])).await.unwrap();
+        assert_eq!(voip_reply(&c).await, voip(VOIP_CONTROL_REPLY, 3, &[0x0F,0x02]));
+        // no reply: error notification of the controller, datagram shorter than the header
+        c.send(&voip(VOIP_CONTROL_COMMAND, 4, &[0x0F,0x01])).await.unwrap();
+        c.send(&[0x01,0x00,0x00]).await.unwrap();
+        c.send(&voip(VOIP_CONTROL_COMMAND, 5, &[0x01])).await.unwrap();
+        assert_eq!(voip_reply(&c).await, voip(VOIP_CONTROL_REPLY, 5, &[0x01]));
+    }
+}